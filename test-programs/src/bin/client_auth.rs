@@ -0,0 +1,44 @@
+use waki::Client;
+
+fn main() {
+    let client = Client::new();
+
+    let resp = client
+        .get("https://httpbin.org/basic-auth/user/passwd")
+        .basic_auth("user", "passwd")
+        .send()
+        .unwrap();
+    assert_eq!(resp.status_code(), 200);
+
+    let resp = client
+        .get("https://httpbin.org/bearer")
+        .bearer_auth("token")
+        .send()
+        .unwrap();
+    assert_eq!(resp.status_code(), 200);
+
+    // httpbin expects the cookie it sets with the challenge
+    for algorithm in ["MD5", "SHA-256"] {
+        let resp = client
+            .get(&format!(
+                "https://httpbin.org/digest-auth/auth/user/passwd/{algorithm}"
+            ))
+            .header("Cookie", "fake=fake_value")
+            .digest_auth("user", "passwd")
+            .send()
+            .unwrap();
+        assert_eq!(resp.status_code(), 200);
+    }
+
+    let resp = Client::new()
+        .token_provider(|| Ok("token".to_string()))
+        .get("https://httpbin.org/bearer")
+        .send()
+        .unwrap();
+    assert_eq!(resp.status_code(), 200);
+    let resp = Client::new()
+        .get("https://httpbin.org/bearer")
+        .send()
+        .unwrap();
+    assert_eq!(resp.status_code(), 401);
+}
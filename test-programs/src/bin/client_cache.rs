@@ -0,0 +1,48 @@
+use std::sync::Arc;
+use waki::{cache::MemoryStore, Client};
+
+fn main() {
+    let store = Arc::new(MemoryStore::new(16));
+    let client = Client::new().cache(store.clone());
+
+    // fresh for 60 seconds
+    let resp = client.get("https://httpbin.org/cache/60").send().unwrap();
+    assert_eq!(resp.status_code(), 200);
+    assert!(resp.header("Age").is_none());
+    let body = resp.body().unwrap();
+    assert_eq!(store.len(), 1);
+    let resp = client.get("https://httpbin.org/cache/60").send().unwrap();
+    assert_eq!(resp.status_code(), 200);
+    assert!(resp.header("Age").is_some());
+    assert_eq!(resp.body().unwrap(), body);
+
+    // revalidated with If-None-Match and If-Modified-Since, answered by 304
+    let resp = client.get("https://httpbin.org/cache").send().unwrap();
+    assert_eq!(resp.status_code(), 200);
+    let body = resp.body().unwrap();
+    assert_eq!(store.len(), 2);
+    let resp = client.get("https://httpbin.org/cache").send().unwrap();
+    assert_eq!(resp.status_code(), 200);
+    assert_eq!(resp.body().unwrap(), body);
+
+    // not stored
+    let resp = client
+        .get("https://httpbin.org/response-headers?Cache-Control=no-store")
+        .send()
+        .unwrap();
+    assert_eq!(resp.status_code(), 200);
+    resp.body().unwrap();
+    assert_eq!(store.len(), 2);
+
+    // a successful unsafe request invalidates the stored responses
+    let url = "https://httpbin.org/response-headers?Cache-Control=max-age=60";
+    let resp = client.get(url).send().unwrap();
+    assert_eq!(resp.status_code(), 200);
+    resp.body().unwrap();
+    assert_eq!(store.len(), 3);
+    let resp = client.post(url).send().unwrap();
+    assert_eq!(resp.status_code(), 200);
+    assert_eq!(store.len(), 2);
+    let resp = client.get(url).send().unwrap();
+    assert!(resp.header("Age").is_none());
+}
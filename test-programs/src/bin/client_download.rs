@@ -0,0 +1,43 @@
+use waki::{Client, ContentRange};
+
+fn main() {
+    let expected = b"abcdefghijklmnopqrstuvwxyz"
+        .iter()
+        .cycle()
+        .take(1024)
+        .copied()
+        .collect::<Vec<_>>();
+
+    let resp = Client::new()
+        .get("https://httpbin.org/range/1024")
+        .range(10..20)
+        .send()
+        .unwrap();
+    assert_eq!(resp.status_code(), 206);
+    let range = resp
+        .header("Content-Range")
+        .unwrap()
+        .to_str()
+        .unwrap()
+        .parse::<ContentRange>()
+        .unwrap();
+    assert_eq!(range, ContentRange::new(10, 19, Some(1024)));
+    assert_eq!(resp.body().unwrap(), &expected[10..20]);
+
+    let mut buf = vec![];
+    let len = Client::new()
+        .download_to("https://httpbin.org/range/1024", &mut buf)
+        .expected_length(1024)
+        .send()
+        .unwrap();
+    assert_eq!(len, 1024);
+    assert_eq!(buf, expected);
+
+    let mut buf = vec![];
+    let err = Client::new()
+        .download_to("https://httpbin.org/range/1024", &mut buf)
+        .expected_length(2048)
+        .send()
+        .unwrap_err();
+    assert!(err.to_string().contains("expected 2048 bytes"));
+}
@@ -0,0 +1,24 @@
+use waki::Client;
+
+fn main() {
+    let addr = std::env::var("SERVER_ADDR").unwrap();
+    let expected = (0..1024).map(|i| (i % 251) as u8).collect::<Vec<_>>();
+
+    // the connection is dropped after 512 bytes, the rest is requested with If-Range
+    let mut buf = vec![];
+    let len = Client::new()
+        .download_to(&format!("http://{addr}/etag"), &mut buf)
+        .expected_length(1024)
+        .send()
+        .unwrap();
+    assert_eq!(len, 1024);
+    assert_eq!(buf, expected);
+
+    // a modification could not be detected without a validator
+    let mut buf = vec![];
+    let err = Client::new()
+        .download_to(&format!("http://{addr}/plain"), &mut buf)
+        .send()
+        .unwrap_err();
+    assert!(err.to_string().contains("cannot be resumed"));
+}
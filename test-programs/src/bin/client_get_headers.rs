@@ -24,4 +24,19 @@ fn main() {
     assert_eq!(data.headers.get("Test").unwrap(), "test");
     assert_eq!(data.headers.get("A").unwrap(), "b");
     assert_eq!(data.headers.get("C").unwrap(), "d");
+
+    // multiple values of the same response header are kept in order
+    let resp = Client::new()
+        .get("https://httpbin.org/response-headers")
+        .query([("X-Multi", "first"), ("X-Multi", "second")])
+        .send()
+        .unwrap();
+    assert_eq!(resp.status_code(), 200);
+    let values = resp
+        .headers()
+        .get_all("X-Multi")
+        .iter()
+        .map(|v| v.to_str().unwrap())
+        .collect::<Vec<_>>();
+    assert_eq!(values, ["first", "second"]);
 }
@@ -0,0 +1,20 @@
+use std::io::BufRead;
+use waki::Client;
+
+fn main() {
+    let resp = Client::new()
+        .get("https://httpbin.org/stream/5")
+        .send()
+        .unwrap();
+    assert_eq!(resp.status_code(), 200);
+
+    let lines = resp
+        .into_reader()
+        .lines()
+        .collect::<std::io::Result<Vec<_>>>()
+        .unwrap();
+    assert_eq!(lines.len(), 5);
+    for (i, line) in lines.iter().enumerate() {
+        assert!(line.contains(&format!("\"id\": {i}")));
+    }
+}
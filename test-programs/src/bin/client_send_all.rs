@@ -0,0 +1,40 @@
+use std::time::{Duration, Instant};
+use waki::Client;
+
+fn main() {
+    let client = Client::new();
+
+    // the requests are sent concurrently, so the total time is close to the one of a single request
+    let start = Instant::now();
+    let mut indexes = client
+        .send_all((0..3).map(|_| client.get("https://httpbin.org/delay/2")))
+        .map(|(index, resp)| {
+            assert_eq!(resp.unwrap().status_code(), 200);
+            index
+        })
+        .collect::<Vec<_>>();
+    assert!(start.elapsed() < Duration::from_secs(6));
+    indexes.sort();
+    assert_eq!(indexes, [0, 1, 2]);
+
+    // per-request timeouts
+    let results = client
+        .send_all([
+            client
+                .get("https://httpbin.org/delay/5")
+                .timeout(Duration::from_secs(1)),
+            client.get("https://httpbin.org/get"),
+        ])
+        .collect::<Vec<_>>();
+    for (index, resp) in results {
+        match index {
+            0 => assert!(resp.is_err()),
+            _ => assert_eq!(resp.unwrap().status_code(), 200),
+        }
+    }
+
+    // dropping a pending response cancels the request
+    let pending = client.get("https://httpbin.org/delay/5").start().unwrap();
+    assert!(!pending.is_ready());
+    drop(pending);
+}
@@ -0,0 +1,30 @@
+use std::time::{Duration, Instant};
+use waki::{rt, Client};
+
+fn main() {
+    rt::block_on(async {
+        let client = Client::new();
+
+        let start = Instant::now();
+        let handles = (0..3)
+            .map(|_| rt::spawn(client.get("https://httpbin.org/delay/2").send_async()))
+            .collect::<Vec<_>>();
+        for handle in handles {
+            let resp = handle.await.unwrap();
+            assert_eq!(resp.status_code(), 200);
+            while let Some(chunk) = resp.chunk_async(1024).await.unwrap() {
+                assert!(!chunk.is_empty());
+            }
+        }
+        // the requests are sent concurrently
+        assert!(start.elapsed() < Duration::from_secs(6));
+
+        let resp = client
+            .post("https://httpbin.org/post")
+            .body("hello")
+            .send_async()
+            .await
+            .unwrap();
+        assert_eq!(resp.status_code(), 200);
+    });
+}
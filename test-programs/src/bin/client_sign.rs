@@ -0,0 +1,35 @@
+use serde::Deserialize;
+use std::collections::HashMap;
+use waki::{
+    sign::{AwsV4Signer, HmacSigner},
+    Client,
+};
+
+#[derive(Deserialize)]
+struct Data {
+    headers: HashMap<String, String>,
+}
+
+fn main() {
+    let resp = Client::new()
+        .signer(HmacSigner::new("secret"))
+        .post("https://httpbin.org/anything")
+        .body("hello")
+        .send()
+        .unwrap();
+    assert_eq!(resp.status_code(), 200);
+    let data = resp.json::<Data>().unwrap();
+    assert_eq!(data.headers.get("X-Signature").unwrap().len(), 64);
+    assert!(data.headers.contains_key("X-Signature-Timestamp"));
+
+    let resp = Client::new()
+        .get("https://httpbin.org/anything")
+        .signer(AwsV4Signer::new("AKID", "SECRET", "us-east-1", "service"))
+        .send()
+        .unwrap();
+    assert_eq!(resp.status_code(), 200);
+    let data = resp.json::<Data>().unwrap();
+    let authorization = data.headers.get("Authorization").unwrap();
+    assert!(authorization.starts_with("AWS4-HMAC-SHA256 Credential=AKID/"));
+    assert!(data.headers.contains_key("X-Amz-Date"));
+}
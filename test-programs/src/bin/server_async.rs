@@ -0,0 +1,22 @@
+use waki::{handler, rt, ErrorCode, Request, Response};
+
+#[handler]
+async fn hello(req: Request) -> Result<Response, ErrorCode> {
+    let greeting = rt::spawn(async { "Hello" });
+
+    let mut body = vec![];
+    while let Some(chunk) = req.chunk_async(1024).await.unwrap() {
+        body.extend(chunk);
+    }
+
+    Response::builder()
+        .body(format!(
+            "{}, {}!",
+            greeting.await,
+            String::from_utf8(body).unwrap()
+        ))
+        .build()
+}
+
+// required since this file is built as a `bin`
+fn main() {}
@@ -0,0 +1,43 @@
+use serde::Deserialize;
+use waki::{
+    auth::{constant_time_eq, AuthGuard},
+    handler, jwt, Authorization, ErrorCode, Request, Response,
+};
+
+#[derive(Deserialize)]
+struct Claims {
+    sub: String,
+}
+
+#[handler]
+fn hello(req: Request) -> Result<Response, ErrorCode> {
+    let user = match req.path() {
+        "/basic" => AuthGuard::basic("waki").check(&req, |credentials| match credentials {
+            Authorization::Basic { username, password }
+                if constant_time_eq(password.as_bytes(), b"passwd") =>
+            {
+                Some(username.clone())
+            }
+            _ => None,
+        }),
+        "/jwt" => AuthGuard::bearer("waki").check(&req, |credentials| match credentials {
+            Authorization::Bearer(token) => jwt::decode::<Claims>(
+                token,
+                &jwt::DecodingKey::from_secret(b"secret"),
+                &jwt::Validation::new().audience("api"),
+            )
+            .ok()
+            .map(|claims| claims.sub),
+            _ => None,
+        }),
+        _ => return Response::builder().status_code(404).build(),
+    };
+
+    match user {
+        Ok(user) => Response::builder().body(format!("Hello, {user}!")).build(),
+        Err(resp) => Ok(resp),
+    }
+}
+
+// required since this file is built as a `bin`
+fn main() {}
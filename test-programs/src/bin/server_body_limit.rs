@@ -0,0 +1,24 @@
+use waki::{handler, set_default_body_limit, BodyLimitExceeded, ErrorCode, Request, Response};
+
+#[handler]
+fn hello(mut req: Request) -> Result<Response, ErrorCode> {
+    set_default_body_limit(16);
+    if req.path() == "/large" {
+        req.set_body_limit(1024);
+    }
+    match req.form() {
+        Ok(form) => Response::builder()
+            .body(format!("{} fields", form.len()))
+            .build(),
+        Err(e) => match e.downcast_ref::<BodyLimitExceeded>() {
+            Some(e) => Response::builder()
+                .status_code(413)
+                .body(e.to_string())
+                .build(),
+            None => Response::builder().status_code(400).build(),
+        },
+    }
+}
+
+// required since this file is built as a `bin`
+fn main() {}
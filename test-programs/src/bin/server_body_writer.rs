@@ -0,0 +1,21 @@
+use waki::{handler, rt, ErrorCode, Request, Response};
+
+#[handler]
+async fn hello(req: Request) -> Result<Response, ErrorCode> {
+    Response::builder()
+        .body_writer(move |mut writer| async move {
+            writer.write_all(b"Hello").await?;
+            writer.flush().await?;
+            // the rest of the body is written while awaiting other tasks and the request body
+            let separator = rt::spawn(async { ", " }).await;
+            writer.write_all(separator.as_bytes()).await?;
+            while let Some(chunk) = req.chunk_async(2).await? {
+                writer.write_all(&chunk).await?;
+            }
+            writer.write_all(b"!").await
+        })
+        .build()
+}
+
+// required since this file is built as a `bin`
+fn main() {}
@@ -0,0 +1,25 @@
+use serde::Deserialize;
+use waki::{handler, ErrorCode, Request, Response};
+
+#[derive(Deserialize)]
+struct Data<'a> {
+    name: &'a str,
+}
+
+#[handler]
+fn hello(mut req: Request) -> Result<Response, ErrorCode> {
+    if req.buffer_with_limit(32).is_err() {
+        return Response::builder().status_code(413).build();
+    }
+    let text = req.text().unwrap();
+    let name = req.json_ref::<Data>().unwrap().name.to_string();
+    let length = req.bytes_ref().unwrap().len();
+    let content_type = req.content_type().unwrap();
+
+    Response::builder()
+        .body(format!("{content_type} {length} {text} {name}"))
+        .build()
+}
+
+// required since this file is built as a `bin`
+fn main() {}
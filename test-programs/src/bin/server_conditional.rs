@@ -0,0 +1,23 @@
+use std::time::{Duration, UNIX_EPOCH};
+use waki::{handler, EntityTag, ErrorCode, Method, Request, Response};
+
+#[handler]
+fn hello(req: Request) -> Result<Response, ErrorCode> {
+    let body = b"Hello, WASI!";
+    let etag = EntityTag::for_bytes(body);
+    let last_modified = UNIX_EPOCH + Duration::from_secs(1_700_000_000);
+    if matches!(req.method(), Method::Put) {
+        // the preconditions of a change are checked before making it
+        if let Some(resp) = req.check_preconditions(Some(&etag), Some(last_modified)) {
+            return Ok(resp);
+        }
+    }
+    Response::builder()
+        .etag(etag)
+        .last_modified(last_modified)
+        .body(body)
+        .build()
+}
+
+// required since this file is built as a `bin`
+fn main() {}
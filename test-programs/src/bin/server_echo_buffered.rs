@@ -0,0 +1,10 @@
+use waki::{handler, ErrorCode, Request, Response};
+
+#[handler]
+fn hello(req: Request) -> Result<Response, ErrorCode> {
+    let body = req.body().unwrap();
+    Response::builder().body(body).build()
+}
+
+// required since this file is built as a `bin`
+fn main() {}
@@ -0,0 +1,9 @@
+use waki::{handler, ErrorCode, Request, Response};
+
+#[handler]
+fn hello(req: Request) -> Result<Response, ErrorCode> {
+    Response::builder().body_reader(req.into_reader()).build()
+}
+
+// required since this file is built as a `bin`
+fn main() {}
@@ -0,0 +1,14 @@
+use std::io::BufRead;
+use waki::{handler, ErrorCode, Request, Response};
+
+#[handler]
+fn hello(req: Request) -> Result<Response, ErrorCode> {
+    let mut reader = req.into_reader();
+    // once a chunk is buffered the body is copied through the component in 64 KiB chunks
+    // instead of being spliced
+    reader.fill_buf().unwrap();
+    Response::builder().body_reader(reader).build()
+}
+
+// required since this file is built as a `bin`
+fn main() {}
@@ -0,0 +1,32 @@
+use serde::{Deserialize, Serialize};
+use waki::{handler, ErrorCode, Request, Response};
+
+#[derive(Clone, PartialEq, Serialize, Deserialize, prost::Message)]
+struct Data {
+    #[prost(string, tag = "1")]
+    name: String,
+}
+
+#[handler]
+fn hello(req: Request) -> Result<Response, ErrorCode> {
+    // the body is decoded and encoded back in the same format
+    let resp = match req.path() {
+        "/cbor" => req
+            .cbor::<Data>()
+            .map(|data| Response::builder().cbor(&data)),
+        "/msgpack" => req
+            .msgpack::<Data>()
+            .map(|data| Response::builder().msgpack(&data)),
+        "/protobuf" => req
+            .protobuf::<Data>()
+            .map(|data| Response::builder().protobuf(&data)),
+        _ => return Response::builder().status_code(404).build(),
+    };
+    match resp {
+        Ok(resp) => resp.build(),
+        Err(_) => Response::builder().status_code(400).build(),
+    }
+}
+
+// required since this file is built as a `bin`
+fn main() {}
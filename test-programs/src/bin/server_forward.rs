@@ -0,0 +1,10 @@
+use waki::{handler, ErrorCode, Request, Response};
+
+#[handler]
+fn hello(req: Request) -> Result<Response, ErrorCode> {
+    req.forward_to("https://httpbin.org/anything")
+        .map_err(|e| ErrorCode::InternalError(Some(e.to_string())))
+}
+
+// required since this file is built as a `bin`
+fn main() {}
@@ -0,0 +1,20 @@
+use serde::{Deserialize, Serialize};
+use waki::{handler, ErrorCode, Request, Response};
+
+#[derive(Serialize, Deserialize)]
+struct Record {
+    n: u32,
+}
+
+#[handler]
+fn hello(req: Request) -> Result<Response, ErrorCode> {
+    // records are doubled one by one while the request body is received
+    let records = req
+        .json_lines::<Record>()
+        .map_while(Result::ok)
+        .map(|record| Record { n: record.n * 2 });
+    Response::builder().json_lines(records).build()
+}
+
+// required since this file is built as a `bin`
+fn main() {}
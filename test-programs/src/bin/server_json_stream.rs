@@ -0,0 +1,30 @@
+use serde::Deserialize;
+use waki::{handler, BodyLimitExceeded, ErrorCode, Request, Response};
+
+#[derive(Deserialize)]
+struct Record {
+    n: u32,
+}
+
+#[handler]
+fn hello(mut req: Request) -> Result<Response, ErrorCode> {
+    let sum = match req.path() {
+        "/array" => req
+            .json_array_stream::<Record>()
+            .map(|record| record.map(|record| record.n))
+            .sum::<Result<u32, _>>(),
+        _ => {
+            req.set_body_limit(16);
+            req.json::<Vec<Record>>()
+                .map(|records| records.iter().map(|record| record.n).sum())
+        }
+    };
+    match sum {
+        Ok(sum) => Response::builder().body(sum.to_string()).build(),
+        Err(e) if e.is::<BodyLimitExceeded>() => Response::builder().status_code(413).build(),
+        Err(_) => Response::builder().status_code(400).build(),
+    }
+}
+
+// required since this file is built as a `bin`
+fn main() {}
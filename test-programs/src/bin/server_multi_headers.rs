@@ -0,0 +1,26 @@
+use waki::{handler, ErrorCode, Request, Response};
+
+#[handler]
+fn hello(req: Request) -> Result<Response, ErrorCode> {
+    // echo the values of the request header, in order
+    let values = req
+        .headers()
+        .get_all("x-multi")
+        .iter()
+        .map(|v| v.to_str().unwrap())
+        .collect::<Vec<_>>();
+
+    let mut builder = Response::builder()
+        .header("x-removed", "value")
+        .append_header("set-cookie", "a=1")
+        .append_header("set-cookie", "b=2")
+        .append_header("link", "</style.css>; rel=preload")
+        .remove_header("x-removed");
+    if let Some(headers) = builder.headers_mut() {
+        headers.append("link", "</script.js>; rel=preload".parse().unwrap());
+    }
+    builder.body(values.join("|")).build()
+}
+
+// required since this file is built as a `bin`
+fn main() {}
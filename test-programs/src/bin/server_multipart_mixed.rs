@@ -0,0 +1,25 @@
+use waki::{
+    handler,
+    multipart::{BodyPart, Multipart, PartBody},
+    ErrorCode, Request, Response,
+};
+
+#[handler]
+fn hello(req: Request) -> Result<Response, ErrorCode> {
+    let mixed = req.multipart_body().unwrap();
+    let mut related = Multipart::related().param("type", "text/plain");
+    for part in mixed.into_parts() {
+        match part.body {
+            PartBody::Bytes(bytes) => related = related.part(BodyPart::new(bytes)),
+            PartBody::Multipart(nested) => {
+                for part in nested.into_parts() {
+                    related = related.part(part);
+                }
+            }
+        }
+    }
+    Response::builder().multipart_body(related).build()
+}
+
+// required since this file is built as a `bin`
+fn main() {}
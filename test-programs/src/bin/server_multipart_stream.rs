@@ -0,0 +1,24 @@
+use std::io::Read;
+use waki::{handler, ErrorCode, Request, Response};
+
+#[handler]
+fn hello(req: Request) -> Result<Response, ErrorCode> {
+    let mut parts = req.multipart_reader().unwrap();
+    let mut summary = vec![];
+    while let Some(mut part) = parts.next_part().unwrap() {
+        let content_type = part.content_type().unwrap();
+        let mut len = 0;
+        let mut buf = [0; 4];
+        loop {
+            match part.read(&mut buf).unwrap() {
+                0 => break,
+                n => len += n,
+            }
+        }
+        summary.push(format!("{content_type}:{len}"));
+    }
+    Response::builder().body(summary.join(",")).build()
+}
+
+// required since this file is built as a `bin`
+fn main() {}
@@ -0,0 +1,26 @@
+use waki::{handler, ErrorCode, Request, Response};
+
+#[handler]
+fn hello(req: Request) -> Result<Response, ErrorCode> {
+    let available = ["application/json", "text/html"];
+    let greeting = match req.negotiate_language(&["en", "fr"]) {
+        Some("fr") => "Bonjour",
+        _ => "Hello",
+    };
+    let builder = match req.negotiate(&available) {
+        Some("text/html") => Response::builder()
+            .header("Content-Type", "text/html")
+            .body(format!("<p>{greeting}</p>")),
+        Some(_) => Response::builder()
+            .header("Content-Type", "application/json")
+            .body(format!(r#"{{"message":"{greeting}"}}"#)),
+        None => return Ok(Response::not_acceptable(&available)),
+    };
+    match req.negotiate_encoding(&["identity"]) {
+        Some(_) => builder.build(),
+        None => Ok(Response::not_acceptable(&["identity"])),
+    }
+}
+
+// required since this file is built as a `bin`
+fn main() {}
@@ -0,0 +1,17 @@
+use std::io::Cursor;
+use std::time::{Duration, UNIX_EPOCH};
+use waki::{handler, mime, EntityTag, ErrorCode, Ranged, Request, Response};
+
+#[handler]
+fn hello(req: Request) -> Result<Response, ErrorCode> {
+    let data = b"0123456789abcdefghijklmnopqrstuvwxyz".to_vec();
+    let len = data.len() as u64;
+    Ranged::new(Cursor::new(data), len)
+        .content_type(mime::TEXT_PLAIN)
+        .etag(EntityTag::strong("v1"))
+        .last_modified(UNIX_EPOCH + Duration::from_secs(1_700_000_000))
+        .respond(&req)
+}
+
+// required since this file is built as a `bin`
+fn main() {}
@@ -0,0 +1,16 @@
+use waki::{
+    fs::{ServeDir, ServeFile},
+    handler, ErrorCode, Request, Response,
+};
+
+#[handler]
+fn hello(req: Request) -> Result<Response, ErrorCode> {
+    // the fixtures directory of the tests is preopened as `.`
+    match req.path() {
+        "/file" => ServeFile::new("file.txt").serve(&req),
+        _ => ServeDir::new("public").prefix("/static").serve(&req),
+    }
+}
+
+// required since this file is built as a `bin`
+fn main() {}
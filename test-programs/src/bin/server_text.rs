@@ -0,0 +1,16 @@
+use waki::{handler, ErrorCode, Request, Response};
+
+#[handler]
+fn hello(mut req: Request) -> Result<Response, ErrorCode> {
+    let text = match req.path() {
+        "/lossy" => req.text_lossy(),
+        _ => req.text(),
+    };
+    match text {
+        Ok(text) => Response::builder().text(text).build(),
+        Err(_) => Response::builder().status_code(400).build(),
+    }
+}
+
+// required since this file is built as a `bin`
+fn main() {}
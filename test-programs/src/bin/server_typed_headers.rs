@@ -0,0 +1,36 @@
+use std::time::{Duration, UNIX_EPOCH};
+use waki::{handler, Authorization, EntityTag, ErrorCode, Request, Response};
+
+#[handler]
+fn hello(req: Request) -> Result<Response, ErrorCode> {
+    let Some(Authorization::Basic { username, .. }) = req.authorization() else {
+        return Response::builder().status_code(401).build();
+    };
+    if req
+        .if_none_match()
+        .is_some_and(|v| v.matches(&EntityTag::strong("v1")))
+    {
+        return Response::builder().status_code(304).build();
+    }
+
+    let accept = req
+        .accept()
+        .into_iter()
+        .map(|v| v.item.to_string())
+        .collect::<Vec<_>>();
+    let date = req.date().unwrap().duration_since(UNIX_EPOCH).unwrap();
+
+    Response::builder()
+        .content_type(mime::TEXT_PLAIN_UTF_8)
+        .date(UNIX_EPOCH + Duration::from_secs(784111777))
+        .body(format!(
+            "{username} {} {} {}",
+            accept.join(","),
+            date.as_secs(),
+            req.content_length().unwrap_or_default()
+        ))
+        .build()
+}
+
+// required since this file is built as a `bin`
+fn main() {}
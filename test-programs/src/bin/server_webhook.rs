@@ -0,0 +1,23 @@
+use serde::Deserialize;
+use waki::{handler, sign::WebhookVerifier, ErrorCode, Request, Response};
+
+#[derive(Deserialize)]
+struct Event {
+    action: String,
+}
+
+#[handler]
+fn hello(mut req: Request) -> Result<Response, ErrorCode> {
+    if req.verify_hmac(&WebhookVerifier::github("secret")).is_err() {
+        return Response::builder().status_code(401).build();
+    }
+    match req.json::<Event>() {
+        Ok(event) => Response::builder()
+            .body(format!("Received {}", event.action))
+            .build(),
+        Err(_) => Response::builder().status_code(400).build(),
+    }
+}
+
+// required since this file is built as a `bin`
+fn main() {}
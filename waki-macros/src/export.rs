@@ -7,6 +7,36 @@ use syn::{ItemFn, Result};
 pub fn handler(input: ItemFn) -> Result<TokenStream> {
     let fn_name = &input.sig.ident;
 
+    let handle = if input.sig.asyncness.is_some() {
+        quote! {
+            ::waki::rt::block_on(async move {
+                match request.try_into() {
+                    Ok(req) => {
+                        let preconditions = ::waki::Preconditions::new(&req);
+                        match #fn_name(req).await {
+                            Ok(resp) => ::waki::handle_response_async(response_out, resp, preconditions).await,
+                            Err(e) => ::waki::bindings::wasi::http::types::ResponseOutparam::set(response_out, Err(e)),
+                        }
+                    }
+                    Err(e) => ::waki::bindings::wasi::http::types::ResponseOutparam::set(response_out, Err(e)),
+                }
+            })
+        }
+    } else {
+        quote! {
+            match request.try_into() {
+                Ok(req) => {
+                    let preconditions = ::waki::Preconditions::new(&req);
+                    match #fn_name(req) {
+                        Ok(resp) => ::waki::handle_response(response_out, resp, preconditions),
+                        Err(e) => ::waki::bindings::wasi::http::types::ResponseOutparam::set(response_out, Err(e)),
+                    }
+                }
+                Err(e) => ::waki::bindings::wasi::http::types::ResponseOutparam::set(response_out, Err(e)),
+            }
+        }
+    };
+
     Ok(dummy::wrap_in_const(quote! {
         #input
 
@@ -16,13 +46,7 @@ pub fn handler(input: ItemFn) -> Result<TokenStream> {
 
         impl ::waki::bindings::exports::wasi::http::incoming_handler::Guest for Component {
             fn handle(request: ::waki::bindings::wasi::http::types::IncomingRequest, response_out: ::waki::bindings::wasi::http::types::ResponseOutparam) {
-                match request.try_into() {
-                    Ok(req) => match #fn_name(req) {
-                        Ok(resp) => ::waki::handle_response(response_out, resp),
-                        Err(e) => ::waki::bindings::wasi::http::types::ResponseOutparam::set(response_out, Err(e)),
-                    }
-                    Err(e) => ::waki::bindings::wasi::http::types::ResponseOutparam::set(response_out, Err(e)),
-                }
+                #handle
             }
         }
     }))
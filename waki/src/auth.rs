@@ -0,0 +1,674 @@
+#[cfg(feature = "digest-auth")]
+use crate::Method;
+#[cfg(feature = "json")]
+use crate::{header::ACCEPT, Client};
+use crate::{
+    header::{HeaderValue, WWW_AUTHENTICATE},
+    Authorization, Request, Response,
+};
+
+use anyhow::Result;
+#[cfg(feature = "digest-auth")]
+use anyhow::{anyhow, Error};
+#[cfg(feature = "json")]
+use std::{
+    sync::Mutex,
+    time::{Duration, Instant},
+};
+
+/// A source of bearer tokens, set on a [`Client`](crate::Client) with
+/// [`token_provider`](crate::Client::token_provider).
+///
+/// The provider is called each time a request is sent, so it is responsible for caching the
+/// token and refreshing it before it expires, as [`ClientCredentials`] does.
+pub trait TokenProvider: Send + Sync {
+    fn token(&self) -> Result<String>;
+}
+
+impl<F> TokenProvider for F
+where
+    F: Fn() -> Result<String> + Send + Sync,
+{
+    #[inline]
+    fn token(&self) -> Result<String> {
+        self()
+    }
+}
+
+/// The OAuth2 client credentials grant, see RFC 6749 section 4.4.
+///
+/// The access token is requested from the token endpoint on first use, then cached until
+/// shortly before it expires, or for 5 minutes when the endpoint does not say when it does.
+///
+/// # Optional
+///
+/// This requires the `json` feature enabled.
+///
+/// ```
+/// # use waki::{auth::ClientCredentials, Client};
+/// # fn run() {
+/// let client = Client::new().token_provider(
+///     ClientCredentials::new("https://auth.example.com/oauth/token", "id", "secret")
+///         .scope("read"),
+/// );
+/// # }
+/// ```
+#[cfg(feature = "json")]
+pub struct ClientCredentials {
+    token_url: String,
+    client_id: String,
+    client_secret: String,
+    scopes: Vec<String>,
+    // the token and the instant it expires at
+    cached: Mutex<Option<(String, Instant)>>,
+}
+
+/// Tokens are refreshed this long before they expire, so they don't expire in flight.
+#[cfg(feature = "json")]
+const EXPIRY_MARGIN: Duration = Duration::from_secs(30);
+
+/// The lifetime of tokens without `expires_in`, so that they are still refreshed.
+#[cfg(feature = "json")]
+const DEFAULT_LIFETIME: Duration = Duration::from_secs(5 * 60);
+
+#[cfg(feature = "json")]
+impl ClientCredentials {
+    pub fn new<U, I, S>(token_url: U, client_id: I, client_secret: S) -> Self
+    where
+        U: Into<String>,
+        I: Into<String>,
+        S: Into<String>,
+    {
+        Self {
+            token_url: token_url.into(),
+            client_id: client_id.into(),
+            client_secret: client_secret.into(),
+            scopes: vec![],
+            cached: Mutex::new(None),
+        }
+    }
+
+    /// Add a scope to request.
+    pub fn scope<S: Into<String>>(mut self, scope: S) -> Self {
+        self.scopes.push(scope.into());
+        self
+    }
+
+    /// Drop the cached token, so the next request gets a new one.
+    pub fn invalidate(&self) {
+        self.cached.lock().unwrap().take();
+    }
+
+    fn fetch(&self) -> Result<(String, Instant)> {
+        let mut form = vec![("grant_type", "client_credentials".to_string())];
+        if !self.scopes.is_empty() {
+            form.push(("scope", self.scopes.join(" ")));
+        }
+        let resp = Client::new()
+            .post(&self.token_url)
+            // the credentials are form-urlencoded first, see RFC 6749 section 2.3.1
+            .basic_auth(
+                form_urlencode(&self.client_id),
+                form_urlencode(&self.client_secret),
+            )
+            .header(ACCEPT, "application/json")
+            .form(form)
+            .send()?;
+        let status = resp.status_code();
+        // the error responses of some endpoints are not JSON
+        if status != 200 {
+            let body = resp.body()?;
+            return Err(anyhow::anyhow!(
+                "token request failed with status {status}: {}",
+                String::from_utf8_lossy(&body)
+            ));
+        }
+        let body = resp.json::<serde_json::Value>()?;
+
+        let token = body["access_token"]
+            .as_str()
+            .ok_or_else(|| anyhow::anyhow!("missing access_token in the token response"))?;
+        let expires_at = Instant::now() + lifetime(&body["expires_in"]);
+        Ok((token.to_string(), expires_at))
+    }
+}
+
+/// How long to cache a token according to its `expires_in`, which some endpoints send as a
+/// string.
+#[cfg(feature = "json")]
+fn lifetime(expires_in: &serde_json::Value) -> Duration {
+    let secs = match expires_in {
+        serde_json::Value::Number(n) => n.as_u64(),
+        serde_json::Value::String(s) => s.trim().parse().ok(),
+        _ => None,
+    };
+    match secs {
+        Some(secs) => Duration::from_secs(secs).saturating_sub(EXPIRY_MARGIN),
+        None => DEFAULT_LIFETIME,
+    }
+}
+
+#[cfg(feature = "json")]
+fn form_urlencode(s: &str) -> String {
+    form_urlencoded::byte_serialize(s.as_bytes()).collect()
+}
+
+#[cfg(feature = "json")]
+impl TokenProvider for ClientCredentials {
+    fn token(&self) -> Result<String> {
+        let mut cached = self.cached.lock().unwrap();
+        match &*cached {
+            Some((token, expires_at)) if Instant::now() < *expires_at => Ok(token.clone()),
+            _ => {
+                let (token, expires_at) = self.fetch()?;
+                *cached = Some((token.clone(), expires_at));
+                Ok(token)
+            }
+        }
+    }
+}
+
+/// Parse the challenges of `WWW-Authenticate` header values, see RFC 9110 section 11.6.1, as
+/// their lowercase scheme and their parameters. The token68 of a challenge is skipped.
+#[cfg(feature = "digest-auth")]
+pub(crate) fn parse_challenges(s: &str) -> Vec<(String, Vec<(String, String)>)> {
+    fn token(s: &str) -> (&str, &str) {
+        let end = s
+            .find(|c: char| !c.is_ascii_alphanumeric() && !"!#$%&'*+-.^_`|~".contains(c))
+            .unwrap_or(s.len());
+        s.split_at(end)
+    }
+
+    fn skip_separators(s: &str) -> &str {
+        s.trim_start_matches(|c: char| c.is_whitespace() || c == ',')
+    }
+
+    let mut challenges = vec![];
+    let mut rest = skip_separators(s);
+    loop {
+        let (scheme, tail) = token(rest);
+        if scheme.is_empty() {
+            break;
+        }
+        rest = tail;
+        let mut params = vec![];
+        loop {
+            let start = skip_separators(rest);
+            let (name, tail) = token(start);
+            let Some(tail) = tail.trim_start().strip_prefix('=') else {
+                // the scheme of the next challenge
+                rest = start;
+                break;
+            };
+            let tail = tail.trim_start();
+            if name.is_empty() || tail.is_empty() || tail.starts_with([',', '=']) {
+                // a token68, padded with `=`
+                rest = tail.trim_start_matches('=');
+                continue;
+            }
+            let value = match tail.strip_prefix('"') {
+                Some(quoted) => {
+                    let mut value = String::new();
+                    let mut chars = quoted.char_indices();
+                    let mut end = quoted.len();
+                    while let Some((i, c)) = chars.next() {
+                        match c {
+                            '\\' => value.extend(chars.next().map(|(_, c)| c)),
+                            '"' => {
+                                end = i + 1;
+                                break;
+                            }
+                            c => value.push(c),
+                        }
+                    }
+                    rest = &quoted[end..];
+                    value
+                }
+                None => {
+                    let (value, tail) = token(tail);
+                    rest = tail;
+                    value.to_string()
+                }
+            };
+            params.push((name.to_ascii_lowercase(), value));
+        }
+        challenges.push((scheme.to_ascii_lowercase(), params));
+        rest = skip_separators(rest);
+    }
+    challenges
+}
+
+/// Quote a parameter value, escaping backslashes and double quotes.
+pub(crate) fn quote(value: &str) -> String {
+    format!("\"{}\"", value.replace('\\', "\\\\").replace('"', "\\\""))
+}
+
+/// Compare two byte strings in constant time, to check secrets such as passwords or tokens
+/// without leaking how much of them matched through timing.
+///
+/// Only the length of the strings may be leaked.
+pub fn constant_time_eq(a: &[u8], b: &[u8]) -> bool {
+    if a.len() != b.len() {
+        return false;
+    }
+    let diff = a.iter().zip(b).fold(0, |acc, (x, y)| acc | (x ^ y));
+    std::hint::black_box(diff) == 0
+}
+
+/// An authentication challenge, the value of a `WWW-Authenticate` header.
+///
+/// ```
+/// # use waki::auth::Challenge;
+/// let challenge = Challenge::bearer("api").param("scope", "read");
+/// assert_eq!(challenge.to_string(), r#"Bearer realm="api", scope="read""#);
+/// ```
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct Challenge {
+    scheme: String,
+    params: Vec<(String, String)>,
+}
+
+impl Challenge {
+    #[inline]
+    pub fn new<S: Into<String>>(scheme: S) -> Self {
+        Self {
+            scheme: scheme.into(),
+            params: vec![],
+        }
+    }
+
+    /// A `Basic` challenge, asking clients to send UTF-8 credentials, see RFC 7617.
+    pub fn basic<R: Into<String>>(realm: R) -> Self {
+        Self::new("Basic")
+            .param("realm", realm)
+            .param("charset", "UTF-8")
+    }
+
+    /// A `Bearer` challenge, see RFC 6750.
+    pub fn bearer<R: Into<String>>(realm: R) -> Self {
+        Self::new("Bearer").param("realm", realm)
+    }
+
+    /// Add a parameter, its value is quoted.
+    pub fn param<N: Into<String>, V: Into<String>>(mut self, name: N, value: V) -> Self {
+        self.params.push((name.into(), value.into()));
+        self
+    }
+
+    #[inline]
+    pub fn scheme(&self) -> &str {
+        &self.scheme
+    }
+}
+
+impl std::fmt::Display for Challenge {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        f.write_str(&self.scheme)?;
+        let params = self
+            .params
+            .iter()
+            .map(|(name, value)| format!("{name}={}", quote(value)))
+            .collect::<Vec<_>>();
+        if !params.is_empty() {
+            write!(f, " {}", params.join(", "))?;
+        }
+        Ok(())
+    }
+}
+
+/// A guard checking the credentials of incoming requests, answering `401 Unauthorized` with
+/// its challenge when they are missing or invalid.
+///
+/// ```
+/// use waki::{auth::{constant_time_eq, AuthGuard}, handler, Authorization, ErrorCode, Request, Response};
+///
+/// #[handler]
+/// fn hello(req: Request) -> Result<Response, ErrorCode> {
+///     let user = match AuthGuard::basic("admin").check(&req, |credentials| match credentials {
+///         Authorization::Basic { username, password }
+///             if constant_time_eq(password.as_bytes(), b"secret") =>
+///         {
+///             Some(username.clone())
+///         }
+///         _ => None,
+///     }) {
+///         Ok(user) => user,
+///         Err(resp) => return Ok(resp),
+///     };
+///     Response::builder().body(format!("Hello, {user}!")).build()
+/// }
+/// ```
+#[derive(Debug, Clone)]
+pub struct AuthGuard {
+    challenge: Challenge,
+}
+
+impl AuthGuard {
+    #[inline]
+    pub fn new(challenge: Challenge) -> Self {
+        Self { challenge }
+    }
+
+    /// A guard accepting `Basic` credentials.
+    #[inline]
+    pub fn basic<R: Into<String>>(realm: R) -> Self {
+        Self::new(Challenge::basic(realm))
+    }
+
+    /// A guard accepting `Bearer` tokens.
+    #[inline]
+    pub fn bearer<R: Into<String>>(realm: R) -> Self {
+        Self::new(Challenge::bearer(realm))
+    }
+
+    /// Check the credentials of `req` with `verify`, which returns the authenticated identity
+    /// if they are valid.
+    ///
+    /// `verify` is only called with credentials of the scheme of the challenge. Otherwise the
+    /// `401` response to return is given back as error.
+    // the response is returned by the handler right away, boxing it would only add noise
+    #[allow(clippy::result_large_err)]
+    pub fn check<T, F>(&self, req: &Request, verify: F) -> Result<T, Response>
+    where
+        F: FnOnce(&Authorization) -> Option<T>,
+    {
+        let Some(credentials) = req.authorization() else {
+            return Err(self.unauthorized());
+        };
+        if !credentials
+            .scheme()
+            .eq_ignore_ascii_case(self.challenge.scheme())
+        {
+            return Err(self.unauthorized());
+        }
+        match verify(&credentials) {
+            Some(identity) => Ok(identity),
+            // RFC 6750 section 3.1
+            None if matches!(credentials, Authorization::Bearer(_)) => Err(Self::new(
+                self.challenge.clone().param("error", "invalid_token"),
+            )
+            .unauthorized()),
+            None => Err(self.unauthorized()),
+        }
+    }
+
+    /// Build the `401 Unauthorized` response carrying the challenge.
+    pub fn unauthorized(&self) -> Response {
+        let mut resp = Response::new();
+        resp.status_code = 401;
+        if let Ok(challenge) = HeaderValue::try_from(self.challenge.to_string()) {
+            resp.headers.insert(WWW_AUTHENTICATE, challenge);
+        }
+        resp
+    }
+}
+
+/// A `Digest` challenge of a `WWW-Authenticate` header, see RFC 7616.
+#[cfg(feature = "digest-auth")]
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub(crate) struct DigestChallenge {
+    realm: String,
+    nonce: String,
+    opaque: Option<String>,
+    algorithm: String,
+    qop: Vec<String>,
+}
+
+#[cfg(feature = "digest-auth")]
+impl DigestChallenge {
+    /// Find the `Digest` challenge of a `401 Unauthorized` response.
+    pub(crate) fn from_response(resp: &Response) -> Option<Self> {
+        if resp.status_code() != 401 {
+            return None;
+        }
+        resp.headers
+            .get_all(WWW_AUTHENTICATE)
+            .iter()
+            .filter_map(|v| v.to_str().ok())
+            .flat_map(parse_challenges)
+            .find(|(scheme, _)| scheme == "digest")
+            .and_then(|(_, params)| Self::from_params(params).ok())
+    }
+
+    /// Compute the `Authorization` header value answering the challenge.
+    pub(crate) fn authorize(
+        &self,
+        username: &str,
+        password: &str,
+        method: &Method,
+        uri: &str,
+        body: &[u8],
+    ) -> Result<String> {
+        use crate::bindings::wasi::random::random::get_random_u64;
+
+        let cnonce = format!("{:016x}{:016x}", get_random_u64(), get_random_u64());
+        self.authorize_with_cnonce(username, password, method, uri, body, &cnonce)
+    }
+
+    fn authorize_with_cnonce(
+        &self,
+        username: &str,
+        password: &str,
+        method: &Method,
+        uri: &str,
+        body: &[u8],
+        cnonce: &str,
+    ) -> Result<String> {
+        let (algorithm, session) = match self.algorithm.strip_suffix("-sess") {
+            Some(algorithm) => (algorithm, true),
+            None => (self.algorithm.as_str(), false),
+        };
+        let hash = |data: &[u8]| -> Result<String> {
+            use md5::Digest;
+            match algorithm.to_ascii_uppercase().as_str() {
+                "MD5" => Ok(format!("{:x}", md5::Md5::digest(data))),
+                "SHA-256" => Ok(format!("{:x}", sha2::Sha256::digest(data))),
+                other => Err(anyhow!("unsupported digest algorithm: {other}")),
+            }
+        };
+        // prefer `auth` over `auth-int`, which requires hashing the body
+        let qop = ["auth", "auth-int"]
+            .into_iter()
+            .find(|qop| self.qop.iter().any(|v| v == qop));
+        let nc = "00000001";
+
+        let mut ha1 = hash(format!("{username}:{}:{password}", self.realm).as_bytes())?;
+        if session {
+            ha1 = hash(format!("{ha1}:{}:{cnonce}", self.nonce).as_bytes())?;
+        }
+        let ha2 = match qop {
+            Some("auth-int") => {
+                hash(format!("{}:{uri}:{}", method.as_str(), hash(body)?).as_bytes())?
+            }
+            _ => hash(format!("{}:{uri}", method.as_str()).as_bytes())?,
+        };
+        let response = match qop {
+            Some(qop) => {
+                hash(format!("{ha1}:{}:{nc}:{cnonce}:{qop}:{ha2}", self.nonce).as_bytes())?
+            }
+            None => hash(format!("{ha1}:{}:{ha2}", self.nonce).as_bytes())?,
+        };
+
+        let mut params = vec![
+            format!("username={}", quote(username)),
+            format!("realm={}", quote(&self.realm)),
+            format!("uri={}", quote(uri)),
+            format!("algorithm={}", self.algorithm),
+            format!("nonce={}", quote(&self.nonce)),
+        ];
+        if let Some(qop) = qop {
+            params.push(format!("nc={nc}"));
+            params.push(format!("cnonce={}", quote(cnonce)));
+            params.push(format!("qop={qop}"));
+        }
+        params.push(format!("response={}", quote(&response)));
+        if let Some(opaque) = &self.opaque {
+            params.push(format!("opaque={}", quote(opaque)));
+        }
+        Ok(format!("Digest {}", params.join(", ")))
+    }
+}
+
+#[cfg(feature = "digest-auth")]
+impl std::str::FromStr for DigestChallenge {
+    type Err = Error;
+
+    /// Parse the `Digest` challenge of a `WWW-Authenticate` header value.
+    fn from_str(s: &str) -> Result<Self> {
+        match parse_challenges(s)
+            .into_iter()
+            .find(|(scheme, _)| scheme == "digest")
+        {
+            Some((_, params)) => Self::from_params(params),
+            None => Err(anyhow!("missing Digest challenge")),
+        }
+    }
+}
+
+#[cfg(feature = "digest-auth")]
+impl DigestChallenge {
+    fn from_params(params: Vec<(String, String)>) -> Result<Self> {
+        let mut challenge = Self {
+            realm: String::new(),
+            nonce: String::new(),
+            opaque: None,
+            algorithm: "MD5".to_string(),
+            qop: vec![],
+        };
+        let mut nonce = None;
+        for (name, value) in params {
+            match name.as_str() {
+                "realm" => challenge.realm = value,
+                "nonce" => nonce = Some(value),
+                "opaque" => challenge.opaque = Some(value),
+                "algorithm" => challenge.algorithm = value,
+                "qop" => challenge.qop = value.split(',').map(|v| v.trim().to_string()).collect(),
+                _ => {}
+            }
+        }
+        challenge.nonce = nonce.ok_or_else(|| anyhow!("missing nonce in the Digest challenge"))?;
+        Ok(challenge)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_constant_time_eq() {
+        assert!(constant_time_eq(b"secret", b"secret"));
+        assert!(!constant_time_eq(b"secret", b"secreT"));
+        assert!(!constant_time_eq(b"secret", b"secret2"));
+        assert!(constant_time_eq(b"", b""));
+    }
+
+    #[test]
+    fn test_challenge() {
+        assert_eq!(
+            Challenge::basic(r#"my "realm""#).to_string(),
+            r#"Basic realm="my \"realm\"", charset="UTF-8""#
+        );
+        assert_eq!(Challenge::new("Negotiate").to_string(), "Negotiate");
+    }
+
+    #[cfg(feature = "json")]
+    #[test]
+    fn test_lifetime() {
+        use serde_json::json;
+
+        assert_eq!(lifetime(&json!(3600)), Duration::from_secs(3570));
+        assert_eq!(lifetime(&json!("3600")), Duration::from_secs(3570));
+        assert_eq!(lifetime(&json!(10)), Duration::ZERO);
+        // a token without a usable expiry is still refreshed
+        assert_eq!(lifetime(&serde_json::Value::Null), DEFAULT_LIFETIME);
+        assert_eq!(lifetime(&json!("soon")), DEFAULT_LIFETIME);
+    }
+
+    #[cfg(feature = "digest-auth")]
+    #[test]
+    fn test_parse_challenges() {
+        let challenges = parse_challenges(
+            r#"Negotiate YWJj==, Basic realm="say \"digest \"", Digest realm="x", nonce="a,b", qop=auth, Bearer"#,
+        );
+        let params = |params: &[(&str, &str)]| {
+            params
+                .iter()
+                .map(|(name, value)| (name.to_string(), value.to_string()))
+                .collect::<Vec<_>>()
+        };
+        assert_eq!(
+            challenges,
+            [
+                ("negotiate".to_string(), vec![]),
+                ("basic".to_string(), params(&[("realm", "say \"digest \"")])),
+                (
+                    "digest".to_string(),
+                    params(&[("realm", "x"), ("nonce", "a,b"), ("qop", "auth")])
+                ),
+                ("bearer".to_string(), vec![]),
+            ]
+        );
+
+        // a realm mentioning "digest " is not a Digest challenge
+        let mut resp = Response::new();
+        resp.status_code = 401;
+        resp.headers.insert(
+            WWW_AUTHENTICATE,
+            r#"Basic realm="digest nonce=1""#.parse().unwrap(),
+        );
+        assert_eq!(DigestChallenge::from_response(&resp), None);
+        resp.headers.append(
+            WWW_AUTHENTICATE,
+            r#"Digest realm="x", nonce="1""#.parse().unwrap(),
+        );
+        assert_eq!(
+            DigestChallenge::from_response(&resp).map(|c| c.nonce),
+            Some("1".to_string())
+        );
+    }
+
+    #[cfg(feature = "digest-auth")]
+    #[test]
+    fn test_digest() -> Result<()> {
+        // example of RFC 7616 section 3.9.1
+        let challenge = r#"Digest realm="http-auth@example.org",
+            qop="auth, auth-int",
+            algorithm=SHA-256,
+            nonce="7ypf/xlj9XXwfDPEoM4URrv/xwf94BcCAzFZH4GiTo0v",
+            opaque="FQhe/qaU925kfnzjCev0ciny7QMkPqMAFRtzCUYo5tdS""#;
+        let mut challenge = challenge.parse::<DigestChallenge>()?;
+        let cnonce = "f2/wE4q74E6zIJEtWaHKaf5wv/H5QzzpXusqGemxURZJ";
+
+        let sha256 = challenge.authorize_with_cnonce(
+            "Mufasa",
+            "Circle of Life",
+            &Method::Get,
+            "/dir/index.html",
+            b"",
+            cnonce,
+        )?;
+        assert!(sha256.contains(
+            r#"response="753927fa0e85d155564e2e272a28d1802ca10daf4496794697cf8db5856cb6c1""#
+        ));
+        assert!(sha256.contains("qop=auth,"));
+        assert!(sha256.contains(r#"opaque="FQhe/qaU925kfnzjCev0ciny7QMkPqMAFRtzCUYo5tdS""#));
+
+        challenge.algorithm = "MD5".to_string();
+        let md5 = challenge.authorize_with_cnonce(
+            "Mufasa",
+            "Circle of Life",
+            &Method::Get,
+            "/dir/index.html",
+            b"",
+            cnonce,
+        )?;
+        assert!(md5.contains(r#"response="8ca523f5e9506fed4657c9700eebdbec""#));
+
+        challenge.algorithm = "SHA-512-256".to_string();
+        assert!(challenge
+            .authorize_with_cnonce("a", "b", &Method::Get, "/", b"", cnonce)
+            .is_err());
+        Ok(())
+    }
+}
@@ -1,31 +1,242 @@
-use crate::bindings::wasi::{
-    http::types::{IncomingBody, InputStream, OutgoingBody},
-    io::streams::StreamError,
+#[cfg(feature = "async")]
+use crate::rt;
+use crate::{
+    bindings::wasi::{
+        http::types::{ErrorCode, IncomingBody, InputStream, OutgoingBody},
+        io::streams::{OutputStream, StreamError},
+    },
+    header::{HeaderMap, CONTENT_LENGTH},
 };
 
 use anyhow::{anyhow, Result};
-use std::io::Read;
+use std::fmt;
+use std::io::{self, BufRead, Read};
+use std::sync::atomic::{AtomicU64, Ordering};
+#[cfg(feature = "cache")]
+use std::sync::Mutex;
+#[cfg(feature = "async")]
+use std::{cell::RefCell, future::Future, pin::Pin, rc::Rc};
 
 /// Default chunk size for streaming writes (64KB)
 const STREAM_CHUNK_SIZE: usize = 65536;
 
+/// Chunk size for reading a whole body into memory (1MB)
+const READ_CHUNK_SIZE: u64 = 1024 * 1024;
+
+/// Default maximum size of an incoming body read into memory (10MB)
+static DEFAULT_BODY_LIMIT: AtomicU64 = AtomicU64::new(10 * 1024 * 1024);
+
+/// Set the maximum size of incoming bodies read into memory, 10MB by default.
+///
+/// It applies to the requests received by a handler and the responses received by a client when
+/// their body is read as a whole, such as with `body()`, `json()`, `form()` or `buffer()`, and can
+/// be overridden for a single request or response with `set_body_limit()`. Streaming the body
+/// with `chunk()` or `into_reader()` is not limited.
+pub fn set_default_body_limit(limit: u64) {
+    DEFAULT_BODY_LIMIT.store(limit, Ordering::Relaxed);
+}
+
+/// The error returned when an incoming body is larger than its limit, see
+/// [`set_default_body_limit`].
+///
+/// Handlers can answer `413 Payload Too Large` when a request body is too large:
+///
+/// ```
+/// use waki::{handler, BodyLimitExceeded, ErrorCode, Request, Response};
+///
+/// #[handler]
+/// fn hello(req: Request) -> Result<Response, ErrorCode> {
+///     match req.form() {
+///         Ok(form) => Response::builder().body(format!("{} fields", form.len())).build(),
+///         Err(e) if e.is::<BodyLimitExceeded>() => Response::builder().status_code(413).build(),
+///         Err(_) => Response::builder().status_code(400).build(),
+///     }
+/// }
+/// ```
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum BodyLimitExceeded {
+    /// The body of a request received by a handler.
+    Request { limit: u64 },
+    /// The body of a response received by a client.
+    Response { limit: u64 },
+}
+
+impl BodyLimitExceeded {
+    #[inline]
+    pub fn limit(&self) -> u64 {
+        match self {
+            Self::Request { limit } | Self::Response { limit } => *limit,
+        }
+    }
+}
+
+impl fmt::Display for BodyLimitExceeded {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            Self::Request { limit } => write!(f, "request body is larger than {limit} bytes"),
+            Self::Response { limit } => write!(f, "response body is larger than {limit} bytes"),
+        }
+    }
+}
+
+impl std::error::Error for BodyLimitExceeded {}
+
+impl From<BodyLimitExceeded> for ErrorCode {
+    fn from(e: BodyLimitExceeded) -> Self {
+        match e {
+            BodyLimitExceeded::Request { limit } => ErrorCode::HttpRequestBodySize(Some(limit)),
+            BodyLimitExceeded::Response { limit } => ErrorCode::HttpResponseBodySize(Some(limit)),
+        }
+    }
+}
+
+/// Whether an incoming body belongs to a request or a response.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub(crate) enum Incoming {
+    Request,
+    Response,
+}
+
+/// A copy of the bytes read from an incoming body, see [`IncomingBodyStream::tee`].
+#[cfg(feature = "cache")]
+pub(crate) trait Tee: Send {
+    /// Copy the next bytes read, returning `false` to stop copying.
+    fn write(&mut self, data: &[u8]) -> bool;
+
+    /// Handle the end of the stream, once every byte was copied.
+    fn end(self: Box<Self>);
+}
+
 pub struct IncomingBodyStream {
     // input-stream resource is a child: it must be dropped before the parent incoming-body is dropped
     input_stream: InputStream,
     _incoming_body: IncomingBody,
+    kind: Incoming,
+    content_length: Option<u64>,
+    limit: u64,
+    #[cfg(feature = "cache")]
+    tee: Mutex<Option<Box<dyn Tee>>>,
 }
 
-impl From<IncomingBody> for IncomingBodyStream {
-    #[inline]
-    fn from(body: IncomingBody) -> Self {
+impl IncomingBodyStream {
+    pub(crate) fn new(body: IncomingBody, kind: Incoming, headers: &HeaderMap) -> Self {
+        let content_length = headers
+            .get(CONTENT_LENGTH)
+            .and_then(|v| v.to_str().ok()?.trim().parse().ok());
         Self {
             // The stream() method can only be called once
             input_stream: body.stream().unwrap(),
             _incoming_body: body,
+            kind,
+            content_length,
+            limit: DEFAULT_BODY_LIMIT.load(Ordering::Relaxed),
+            #[cfg(feature = "cache")]
+            tee: Mutex::default(),
+        }
+    }
+
+    /// Copy the bytes read from the stream to `tee`, which is dropped without being ended when
+    /// a read fails or the stream is dropped before its end.
+    #[cfg(feature = "cache")]
+    pub(crate) fn tee(&mut self, tee: Box<dyn Tee>) {
+        *self.tee.get_mut().unwrap() = Some(tee);
+    }
+
+    #[cfg(feature = "cache")]
+    fn is_teed(&self) -> bool {
+        self.tee.lock().unwrap().is_some()
+    }
+
+    /// Read up to `len` bytes, `None` at the end of the stream.
+    fn read(&self, len: u64) -> Result<Option<Vec<u8>>, StreamError> {
+        let chunk = match self.input_stream.blocking_read(len) {
+            Ok(c) => Ok(Some(c)),
+            Err(StreamError::Closed) => Ok(None),
+            Err(e) => Err(e),
+        };
+        #[cfg(feature = "cache")]
+        self.copy(&chunk);
+        chunk
+    }
+
+    #[cfg(feature = "async")]
+    async fn read_async(&self, len: u64) -> Result<Option<Vec<u8>>, StreamError> {
+        let chunk = loop {
+            match self.input_stream.read(len) {
+                Ok(c) if c.is_empty() => rt::wait(self.input_stream.subscribe()).await,
+                Ok(c) => break Ok(Some(c)),
+                Err(StreamError::Closed) => break Ok(None),
+                Err(e) => break Err(e),
+            }
+        };
+        #[cfg(feature = "cache")]
+        self.copy(&chunk);
+        chunk
+    }
+
+    #[cfg(feature = "cache")]
+    fn copy(&self, chunk: &Result<Option<Vec<u8>>, StreamError>) {
+        let mut tee = self.tee.lock().unwrap();
+        match chunk {
+            Ok(Some(data)) => {
+                if tee.as_mut().is_some_and(|tee| !tee.write(data)) {
+                    *tee = None;
+                }
+            }
+            Ok(None) => {
+                if let Some(tee) = tee.take() {
+                    tee.end();
+                }
+            }
+            Err(_) => *tee = None,
+        }
+    }
+
+    fn exceeded(&self, limit: u64) -> BodyLimitExceeded {
+        match self.kind {
+            Incoming::Request => BodyLimitExceeded::Request { limit },
+            Incoming::Response => BodyLimitExceeded::Response { limit },
+        }
+    }
+
+    /// Read the whole stream, failing early if `Content-Length` is already over `limit`.
+    fn check_content_length(&self, limit: u64) -> Result<()> {
+        if self.content_length.is_some_and(|len| len > limit) {
+            return Err(self.exceeded(limit).into());
+        }
+        Ok(())
+    }
+
+    fn read_to_end(&self, limit: u64) -> Result<Vec<u8>> {
+        self.check_content_length(limit)?;
+        let mut body = Vec::new();
+        loop {
+            // read at most one byte past the limit to detect bodies larger than it
+            let len = READ_CHUNK_SIZE.min(limit.saturating_add(1) - body.len() as u64);
+            match self.read(len).map_err(read_failed)? {
+                Some(mut chunk) => body.append(&mut chunk),
+                None => return Ok(body),
+            }
+            if body.len() as u64 > limit {
+                return Err(self.exceeded(limit).into());
+            }
         }
     }
 }
 
+fn read_failed(e: StreamError) -> anyhow::Error {
+    anyhow!("input_stream read failed: {e:?}")
+}
+
+/// Rebuild the error of a [`Body::Failed`] body, which stays a [`BodyLimitExceeded`] if it was.
+fn failed(exceeded: &Option<BodyLimitExceeded>, e: &str) -> anyhow::Error {
+    let context = format!("the body failed to be read earlier: {e}");
+    match exceeded {
+        Some(exceeded) => anyhow::Error::new(*exceeded).context(context),
+        None => anyhow!(context),
+    }
+}
+
 impl InputStream {
     pub fn chunk(&self, len: u64) -> Result<Option<Vec<u8>>> {
         match self.blocking_read(len) {
@@ -34,6 +245,18 @@ impl InputStream {
             Err(e) => Err(anyhow!("input_stream read failed: {e:?}"))?,
         }
     }
+
+    #[cfg(feature = "async")]
+    pub async fn chunk_async(&self, len: u64) -> Result<Option<Vec<u8>>> {
+        loop {
+            match self.read(len) {
+                Ok(c) if c.is_empty() => rt::wait(self.subscribe()).await,
+                Ok(c) => return Ok(Some(c)),
+                Err(StreamError::Closed) => return Ok(None),
+                Err(e) => Err(anyhow!("input_stream read failed: {e:?}"))?,
+            }
+        }
+    }
 }
 
 pub enum Body {
@@ -41,6 +264,60 @@ pub enum Body {
     Stream(IncomingBodyStream),
     /// A reader for streaming outgoing request bodies
     Reader(Box<dyn Read + Send>),
+    /// A future writing an outgoing body as it runs
+    #[cfg(feature = "async")]
+    Writer(WriteBody),
+    /// A body which failed to be read into memory, so that later reads fail too
+    Failed(Option<BodyLimitExceeded>, String),
+}
+
+/// The future writing a [`Body::Writer`] body.
+#[cfg(feature = "async")]
+pub(crate) type WriteBody =
+    Box<dyn FnOnce(BodyWriter) -> Pin<Box<dyn Future<Output = Result<()>>>> + Send>;
+
+/// A writer for an outgoing body, passed to the future set with `body_writer()` on a builder.
+///
+/// Writes wait for the outgoing stream without blocking other tasks, so the body can be streamed
+/// while awaiting other I/O. The body ends when the future completes.
+///
+/// # Optional
+///
+/// This requires the `async` feature enabled.
+#[cfg(feature = "async")]
+pub struct BodyWriter {
+    sink: Sink,
+}
+
+#[cfg(feature = "async")]
+enum Sink {
+    Stream(Rc<OutputStream>),
+    /// The body is read into memory instead of being sent, such as by `body()`.
+    Buffer(Rc<RefCell<Vec<u8>>>),
+}
+
+#[cfg(feature = "async")]
+impl BodyWriter {
+    /// Write all of `data`.
+    pub async fn write_all(&mut self, data: &[u8]) -> Result<()> {
+        match &self.sink {
+            Sink::Stream(out) => write_all_async(out, data).await,
+            Sink::Buffer(buf) => {
+                buf.borrow_mut().extend_from_slice(data);
+                Ok(())
+            }
+        }
+    }
+
+    /// Flush the bytes written so far, waiting until they are sent.
+    pub async fn flush(&mut self) -> Result<()> {
+        if let Sink::Stream(out) = &self.sink {
+            out.flush()?;
+            rt::wait(out.subscribe()).await;
+            let _ = out.check_write()?;
+        }
+        Ok(())
+    }
 }
 
 impl Body {
@@ -48,21 +325,48 @@ impl Body {
     pub fn chunk(&self, len: u64) -> Result<Option<Vec<u8>>> {
         match &self {
             Body::Bytes(_) => Ok(None),
-            Body::Stream(s) => s.input_stream.chunk(len),
-            Body::Reader(_) => Ok(None), // Reader is for outgoing, not incoming
+            Body::Stream(s) => s.read(len).map_err(read_failed),
+            Body::Failed(exceeded, e) => Err(failed(exceeded, e)),
+            _ => Ok(None), // readers and writers are for outgoing, not incoming
         }
     }
 
+    #[cfg(feature = "async")]
+    #[inline]
+    pub async fn chunk_async(&self, len: u64) -> Result<Option<Vec<u8>>> {
+        match &self {
+            Body::Stream(s) => s.read_async(len).await.map_err(read_failed),
+            Body::Failed(exceeded, e) => Err(failed(exceeded, e)),
+            _ => Ok(None),
+        }
+    }
+
+    /// Read a chunk of the body into a caller-provided buffer, returning 0 at the end of the
+    /// stream.
+    pub fn read_chunk(&self, buf: &mut [u8]) -> Result<usize> {
+        match &self {
+            Body::Stream(s) => match s.read(buf.len() as u64).map_err(read_failed)? {
+                Some(chunk) => {
+                    buf[..chunk.len()].copy_from_slice(&chunk);
+                    Ok(chunk.len())
+                }
+                None => Ok(0),
+            },
+            Body::Failed(exceeded, e) => Err(failed(exceeded, e)),
+            _ => Ok(0),
+        }
+    }
+
+    #[inline]
     pub fn bytes(self) -> Result<Vec<u8>> {
+        self.bytes_with_limit(None)
+    }
+
+    /// Read the whole body, an incoming one being limited to `limit` or to its own limit.
+    fn bytes_with_limit(self, limit: Option<u64>) -> Result<Vec<u8>> {
         match self {
             Body::Bytes(data) => Ok(data),
-            Body::Stream(s) => {
-                let mut body = Vec::new();
-                while let Some(mut chunk) = s.input_stream.chunk(1024 * 1024)? {
-                    body.append(&mut chunk);
-                }
-                Ok(body)
-            }
+            Body::Stream(s) => s.read_to_end(limit.unwrap_or(s.limit)),
             Body::Reader(mut reader) => {
                 let mut body = Vec::new();
                 reader
@@ -70,10 +374,208 @@ impl Body {
                     .map_err(|e| anyhow!("Failed to read body: {e}"))?;
                 Ok(body)
             }
+            #[cfg(feature = "async")]
+            Body::Writer(write) => {
+                let buf = Rc::default();
+                rt::block_on(write(BodyWriter {
+                    sink: Sink::Buffer(Rc::clone(&buf)),
+                }))?;
+                Ok(buf.take())
+            }
+            Body::Failed(exceeded, e) => Err(failed(&exceeded, &e)),
+        }
+    }
+
+    /// Read the body into memory, unless it already is, an incoming one being limited to
+    /// `limit` or to its own limit.
+    pub(crate) fn buffer(&mut self, limit: Option<u64>) -> Result<&[u8]> {
+        if !matches!(self, Body::Bytes(_)) {
+            let body = std::mem::replace(self, Body::Bytes(vec![]));
+            *self = match body.bytes_with_limit(limit) {
+                Ok(data) => Body::Bytes(data),
+                Err(e) => {
+                    let exceeded = e.downcast_ref::<BodyLimitExceeded>().copied();
+                    *self = Body::Failed(exceeded, e.to_string());
+                    return Err(e);
+                }
+            };
+        }
+        match self {
+            Body::Bytes(data) => Ok(data),
+            _ => unreachable!(),
+        }
+    }
+
+    /// Turn the body into a reader failing once an incoming body goes over its limit.
+    #[cfg(feature = "json")]
+    pub(crate) fn into_limited_reader(self) -> Result<LimitedReader> {
+        if let Body::Stream(s) = &self {
+            s.check_content_length(s.limit)?;
+        }
+        let exceeded = self.limit_exceeded();
+        Ok(LimitedReader {
+            reader: BodyReader::new(self),
+            remaining: exceeded.map_or(u64::MAX, |e| e.limit()),
+            exceeded,
+        })
+    }
+
+    /// Get the error returned once an incoming body goes over its limit, none for other bodies.
+    #[cfg(feature = "json")]
+    pub(crate) fn limit_exceeded(&self) -> Option<BodyLimitExceeded> {
+        match self {
+            Body::Stream(s) => Some(s.exceeded(s.limit)),
+            _ => None,
+        }
+    }
+
+    /// Set the maximum size of an incoming body read into memory.
+    #[inline]
+    pub(crate) fn set_limit(&mut self, limit: u64) {
+        if let Body::Stream(s) = self {
+            s.limit = limit;
+        }
+    }
+
+    /// Get back a body held in memory after it was handed over with a request.
+    #[cfg(feature = "digest-auth")]
+    pub(crate) fn into_kept_bytes(self) -> Vec<u8> {
+        match self {
+            Body::Bytes(data) => data,
+            _ => unreachable!("signers leave bodies held in memory untouched"),
+        }
+    }
+
+    /// Get the body if it is held in memory.
+    #[inline]
+    pub(crate) fn as_bytes(&self) -> Option<&[u8]> {
+        match self {
+            Body::Bytes(data) => Some(data),
+            _ => None,
+        }
+    }
+}
+
+/// A reader over a [`Body`] failing with [`BodyLimitExceeded`] once an incoming body goes over
+/// its limit.
+#[cfg(feature = "json")]
+pub(crate) struct LimitedReader {
+    reader: BodyReader,
+    remaining: u64,
+    exceeded: Option<BodyLimitExceeded>,
+}
+
+#[cfg(feature = "json")]
+impl Read for LimitedReader {
+    fn read(&mut self, buf: &mut [u8]) -> io::Result<usize> {
+        let n = self.reader.read(buf)?;
+        match self.exceeded {
+            Some(e) if n as u64 > self.remaining => Err(io::Error::other(e)),
+            _ => {
+                self.remaining = self.remaining.saturating_sub(n as u64);
+                Ok(n)
+            }
         }
     }
 }
 
+/// A reader over a [`Body`], implementing [`Read`] and [`BufRead`].
+///
+/// Reading an incoming body blocks until at least one byte is available, the end of the stream
+/// is reported as EOF and stream failures are surfaced as [`io::Error`].
+pub struct BodyReader {
+    body: Body,
+    buf: Vec<u8>,
+    pos: usize,
+}
+
+impl BodyReader {
+    pub(crate) fn new(mut body: Body) -> Self {
+        let buf = match &mut body {
+            Body::Bytes(data) => std::mem::take(data),
+            _ => vec![],
+        };
+        Self { body, buf, pos: 0 }
+    }
+
+    /// Turn the reader back into a body, keeping the incoming stream when nothing is buffered
+    /// so that it can still be spliced.
+    pub(crate) fn into_body(self) -> Body {
+        if self.pos < self.buf.len() {
+            Body::Reader(Box::new(self))
+        } else {
+            self.body
+        }
+    }
+}
+
+impl Read for BodyReader {
+    fn read(&mut self, buf: &mut [u8]) -> io::Result<usize> {
+        let available = self.fill_buf()?;
+        let n = available.len().min(buf.len());
+        buf[..n].copy_from_slice(&available[..n]);
+        self.consume(n);
+        Ok(n)
+    }
+}
+
+impl BufRead for BodyReader {
+    fn fill_buf(&mut self) -> io::Result<&[u8]> {
+        if self.pos >= self.buf.len() {
+            self.pos = 0;
+            match &mut self.body {
+                Body::Bytes(_) => self.buf.clear(),
+                Body::Stream(s) => {
+                    self.buf = s
+                        .read(STREAM_CHUNK_SIZE as u64)
+                        .map_err(|e| io::Error::other(read_failed(e)))?
+                        .unwrap_or_default();
+                }
+                Body::Reader(reader) => {
+                    self.buf.resize(STREAM_CHUNK_SIZE, 0);
+                    let n = reader.read(&mut self.buf)?;
+                    self.buf.truncate(n);
+                }
+                #[cfg(feature = "async")]
+                Body::Writer(_) => {
+                    let body = std::mem::replace(&mut self.body, Body::Bytes(vec![]));
+                    self.buf = body.bytes().map_err(io::Error::other)?;
+                }
+                Body::Failed(exceeded, e) => return Err(io::Error::other(failed(exceeded, e))),
+            }
+        }
+        Ok(&self.buf[self.pos..])
+    }
+
+    fn consume(&mut self, amt: usize) {
+        self.pos = (self.pos + amt).min(self.buf.len());
+    }
+}
+
+/// Read a teed stream through the component instead of splicing it, so that the copy sees every
+/// byte.
+#[cfg(feature = "cache")]
+fn unsplice(body: Body) -> Body {
+    match body {
+        Body::Stream(s) if s.is_teed() => Body::Reader(Box::new(BodyReader::new(Body::Stream(s)))),
+        body => body,
+    }
+}
+
+/// Write a body to an outgoing body, picking the cheapest way to copy it.
+pub(crate) fn body_to_outgoing_body(outgoing_body: &OutgoingBody, body: Body) -> Result<()> {
+    #[cfg(feature = "cache")]
+    let body = unsplice(body);
+    match body {
+        Body::Bytes(data) => write_to_outgoing_body(outgoing_body, data.as_slice()),
+        Body::Stream(s) => splice_to_outgoing_body(outgoing_body, &s.input_stream),
+        Body::Reader(mut reader) => stream_to_outgoing_body(outgoing_body, reader.as_mut()),
+        Body::Failed(exceeded, e) => Err(failed(&exceeded, &e)),
+        #[cfg(feature = "async")]
+        body @ Body::Writer(_) => rt::block_on(body_to_outgoing_body_async(outgoing_body, body)),
+    }
+}
+
 pub(crate) fn write_to_outgoing_body(outgoing_body: &OutgoingBody, mut buf: &[u8]) -> Result<()> {
     if buf.is_empty() {
         return Ok(());
@@ -145,3 +647,186 @@ pub(crate) fn stream_to_outgoing_body(
     let _ = out.check_write()?;
     Ok(())
 }
+
+/// Splice an incoming stream into an outgoing body.
+///
+/// The data is moved between the WASI streams by the host, without being copied through the
+/// memory of the component.
+pub(crate) fn splice_to_outgoing_body(
+    outgoing_body: &OutgoingBody,
+    input_stream: &InputStream,
+) -> Result<()> {
+    let out = outgoing_body
+        .write()
+        .map_err(|_| anyhow!("outgoing request write failed"))?;
+
+    loop {
+        match out.blocking_splice(input_stream, STREAM_CHUNK_SIZE as u64) {
+            Ok(_) => {}
+            Err(StreamError::Closed) => {
+                check_output_open(&out)?;
+                break;
+            }
+            Err(e) => Err(anyhow!("splice to outgoing body failed: {e:?}"))?,
+        }
+    }
+
+    out.blocking_flush()?;
+    Ok(())
+}
+
+/// Check that a splice ended because its input was closed, not its output, which would lose the
+/// rest of the body.
+fn check_output_open(out: &OutputStream) -> Result<()> {
+    match out.check_write() {
+        Err(StreamError::Closed) => Err(anyhow!("outgoing body closed before the end of the body")),
+        Err(e) => Err(anyhow!("splice to outgoing body failed: {e:?}")),
+        Ok(_) => Ok(()),
+    }
+}
+
+/// Write a body to an outgoing body, waiting for the streams without blocking other tasks.
+#[cfg(feature = "async")]
+pub(crate) async fn body_to_outgoing_body_async(
+    outgoing_body: &OutgoingBody,
+    body: Body,
+) -> Result<()> {
+    let out = Rc::new(
+        outgoing_body
+            .write()
+            .map_err(|_| anyhow!("outgoing request write failed"))?,
+    );
+
+    #[cfg(feature = "cache")]
+    let body = unsplice(body);
+    match body {
+        Body::Bytes(data) => write_all_async(&out, &data).await?,
+        Body::Stream(s) => loop {
+            rt::wait(out.subscribe()).await;
+            rt::wait(s.input_stream.subscribe()).await;
+            match out.splice(&s.input_stream, STREAM_CHUNK_SIZE as u64) {
+                Ok(_) => {}
+                Err(StreamError::Closed) => {
+                    check_output_open(&out)?;
+                    break;
+                }
+                Err(e) => Err(anyhow!("splice to outgoing body failed: {e:?}"))?,
+            }
+        },
+        Body::Reader(mut reader) => {
+            let mut buf = vec![0u8; STREAM_CHUNK_SIZE];
+            loop {
+                let bytes_read = reader
+                    .read(&mut buf)
+                    .map_err(|e| anyhow!("Failed to read from body source: {e}"))?;
+                if bytes_read == 0 {
+                    break;
+                }
+                write_all_async(&out, &buf[..bytes_read]).await?;
+            }
+        }
+        Body::Writer(write) => {
+            write(BodyWriter {
+                sink: Sink::Stream(Rc::clone(&out)),
+            })
+            .await?
+        }
+        Body::Failed(exceeded, e) => return Err(failed(&exceeded, &e)),
+    }
+
+    out.flush()?;
+    rt::wait(out.subscribe()).await;
+    let _ = out.check_write()?;
+    Ok(())
+}
+
+/// Write bytes to an outgoing body, waiting for the stream without blocking other tasks.
+#[cfg(all(feature = "digest-auth", feature = "async"))]
+pub(crate) async fn write_to_outgoing_body_async(
+    outgoing_body: &OutgoingBody,
+    data: &[u8],
+) -> Result<()> {
+    let out = outgoing_body
+        .write()
+        .map_err(|_| anyhow!("outgoing request write failed"))?;
+    write_all_async(&out, data).await?;
+    out.flush()?;
+    rt::wait(out.subscribe()).await;
+    let _ = out.check_write()?;
+    Ok(())
+}
+
+#[cfg(feature = "async")]
+async fn write_all_async(out: &OutputStream, mut buf: &[u8]) -> Result<()> {
+    while !buf.is_empty() {
+        rt::wait(out.subscribe()).await;
+
+        let permit = out.check_write()?;
+        let len = buf.len().min(permit as usize);
+        let (chunk, rest) = buf.split_at(len);
+        buf = rest;
+
+        out.write(chunk)?;
+    }
+    Ok(())
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    struct Failing;
+
+    impl Read for Failing {
+        fn read(&mut self, _buf: &mut [u8]) -> io::Result<usize> {
+            Err(io::Error::other("connection reset"))
+        }
+    }
+
+    #[test]
+    fn test_buffer_failed() {
+        let mut body = Body::Reader(Box::new(Failing));
+        assert!(body.buffer(None).is_err());
+        // later reads fail instead of returning an empty body
+        assert!(body.buffer(None).is_err());
+        assert!(body.as_bytes().is_none());
+        assert!(body.chunk(1024).is_err());
+        assert!(BodyReader::new(body).read(&mut [0; 16]).is_err());
+    }
+
+    #[test]
+    fn test_buffer_failed_limit() {
+        let exceeded = BodyLimitExceeded::Request { limit: 8 };
+        let mut body = Body::Failed(Some(exceeded), exceeded.to_string());
+        // the limit error can still be matched to answer 413
+        let e = body.buffer(None).unwrap_err();
+        assert_eq!(e.downcast_ref::<BodyLimitExceeded>(), Some(&exceeded));
+        assert!(body.chunk(1024).unwrap_err().is::<BodyLimitExceeded>());
+    }
+
+    #[cfg(feature = "async")]
+    #[test]
+    fn test_body_writer() -> Result<()> {
+        use crate::rt;
+
+        let body = Body::Writer(Box::new(|mut writer| {
+            Box::pin(async move {
+                writer.write_all(b"Hello").await?;
+                let separator = rt::spawn(async { ", " }).await;
+                writer.write_all(separator.as_bytes()).await?;
+                writer.flush().await?;
+                writer.write_all(b"WASI!").await
+            })
+        }));
+        assert_eq!(body.bytes()?, b"Hello, WASI!");
+
+        let body = Body::Writer(Box::new(|mut writer| {
+            Box::pin(async move {
+                writer.write_all(b"partial").await?;
+                Err(anyhow!("failed"))
+            })
+        }));
+        assert!(body.bytes().is_err());
+        Ok(())
+    }
+}
@@ -0,0 +1,857 @@
+//! HTTP caching for the requests of a [`Client`](crate::Client), see RFC 9111.
+//!
+//! Responses to `GET` requests are stored in a [`CacheStore`] and reused while they are fresh,
+//! according to their `Cache-Control: max-age` or `Expires` header. Stale responses are
+//! revalidated with their `ETag` or `Last-Modified` header, and a `304 Not Modified` answer is
+//! turned back into the stored response. Responses are stored per variant of their `Vary`
+//! header, except when `no-store` is set on the request or the response. Successful unsafe
+//! requests, such as `POST`, invalidate the responses stored for their URI.
+//!
+//! A response is stored while its body is read by the caller, once it was read to the end, and
+//! only if the body is not larger than [`CacheStore::max_entry_size`]. Responses to requests with
+//! an `Authorization` header are only stored when `Cache-Control` explicitly allows it with
+//! `public`, `s-maxage` or `must-revalidate`, see RFC 9111 section 3.5, as a client can forward
+//! the requests of several users.
+//!
+//! ```
+//! # use anyhow::Result;
+//! # use waki::{cache::MemoryStore, Client};
+//! # fn run() -> Result<()> {
+//! let client = Client::new().cache(MemoryStore::new(128));
+//! // sent to the server
+//! let resp = client.get("https://example.com/config.json").send()?;
+//! // answered from the cache while the response is fresh
+//! let resp = client.get("https://example.com/config.json").send()?;
+//! # Ok(())
+//! # }
+//! ```
+
+use crate::{
+    body::{Body, Tee},
+    header::{
+        HeaderMap, HeaderName, HeaderValue, AGE, AUTHORIZATION, CACHE_CONTROL, CONTENT_LENGTH,
+        DATE, ETAG, EXPIRES, IF_MATCH, IF_MODIFIED_SINCE, IF_NONE_MATCH, IF_RANGE,
+        IF_UNMODIFIED_SINCE, LAST_MODIFIED, RANGE, VARY,
+    },
+    EntityTag, Method, Request, Response,
+};
+
+use anyhow::{anyhow, Result};
+use http::uri::Parts;
+use std::collections::HashMap;
+use std::fs;
+use std::io;
+use std::path::PathBuf;
+use std::sync::{Arc, Mutex};
+use std::time::{Duration, SystemTime, UNIX_EPOCH};
+
+/// The status codes of the responses that can be stored, see RFC 9110 section 15.1.
+const CACHEABLE_STATUS_CODES: [u16; 11] = [200, 203, 204, 300, 301, 308, 404, 405, 410, 414, 501];
+
+/// Default maximum size of the body of a stored response (1MB)
+const DEFAULT_MAX_ENTRY_SIZE: u64 = 1024 * 1024;
+
+/// A response stored in a cache.
+#[derive(Debug, Clone, PartialEq)]
+pub struct CachedResponse {
+    pub status_code: u16,
+    pub headers: HeaderMap,
+    pub body: Vec<u8>,
+    /// The values of the request headers named by the `Vary` header of the response.
+    pub vary: HeaderMap,
+    /// When the request was sent.
+    pub request_time: SystemTime,
+    /// When the response was received.
+    pub response_time: SystemTime,
+}
+
+impl CachedResponse {
+    /// Check whether the response was selected by the same request headers as `headers`.
+    fn matches(&self, headers: &HeaderMap) -> bool {
+        vary_names(&self.headers).iter().all(|name| {
+            headers
+                .get_all(name)
+                .iter()
+                .eq(self.vary.get_all(name).iter())
+        })
+    }
+
+    /// Compute how long the response is fresh, see RFC 9111 section 4.2.1.
+    fn freshness_lifetime(&self) -> Duration {
+        let cache_control = CacheControl::parse(&self.headers);
+        if cache_control.no_cache {
+            return Duration::ZERO;
+        }
+        if let Some(max_age) = cache_control.max_age {
+            return Duration::from_secs(max_age);
+        }
+        let date = header_date(&self.headers, DATE).unwrap_or(self.response_time);
+        if self.headers.contains_key(EXPIRES) {
+            // an invalid date means that the response is already expired
+            return header_date(&self.headers, EXPIRES)
+                .and_then(|expires| expires.duration_since(date).ok())
+                .unwrap_or_default();
+        }
+        // a heuristic freshness, see RFC 9111 section 4.2.2
+        header_date(&self.headers, LAST_MODIFIED)
+            .and_then(|last_modified| date.duration_since(last_modified).ok())
+            .map(|d| d / 10)
+            .unwrap_or_default()
+    }
+
+    /// Compute the age of the response at `now`, see RFC 9111 section 4.2.3.
+    fn age(&self, now: SystemTime) -> Duration {
+        let date = header_date(&self.headers, DATE).unwrap_or(self.response_time);
+        let apparent_age = self.response_time.duration_since(date).unwrap_or_default();
+        let age_value = self
+            .headers
+            .get(AGE)
+            .and_then(|v| v.to_str().ok()?.parse().ok())
+            .map(Duration::from_secs)
+            .unwrap_or_default();
+        let response_delay = self
+            .response_time
+            .duration_since(self.request_time)
+            .unwrap_or_default();
+        let resident_time = now.duration_since(self.response_time).unwrap_or_default();
+        apparent_age.max(age_value + response_delay) + resident_time
+    }
+
+    fn to_response(&self, age: Duration) -> Response {
+        let mut resp = Response::new();
+        resp.status_code = self.status_code;
+        resp.headers = self.headers.clone();
+        resp.headers.insert(AGE, HeaderValue::from(age.as_secs()));
+        resp.body = Body::Bytes(self.body.clone());
+        resp
+    }
+}
+
+/// A storage for the responses of a cache, set on a [`Client`](crate::Client) with
+/// [`cache`](crate::Client::cache).
+///
+/// The responses of a URI are stored together under a key, one for each variant selected by
+/// their `Vary` header. Errors of the store are not returned to the caller of `send`: the
+/// request is then sent as if the cache was empty.
+pub trait CacheStore: Send + Sync {
+    /// Get the responses stored under `key`, none if there are no such responses.
+    fn get(&self, key: &str) -> Result<Vec<CachedResponse>>;
+
+    /// Replace the responses stored under `key`.
+    fn put(&self, key: &str, responses: Vec<CachedResponse>) -> Result<()>;
+
+    /// Remove the responses stored under `key`.
+    fn remove(&self, key: &str) -> Result<()>;
+
+    /// Get the maximum size of the body of a stored response, larger responses being passed
+    /// through.
+    ///
+    /// Default value: 1MB.
+    fn max_entry_size(&self) -> u64 {
+        DEFAULT_MAX_ENTRY_SIZE
+    }
+}
+
+impl<S: CacheStore + ?Sized> CacheStore for Arc<S> {
+    #[inline]
+    fn get(&self, key: &str) -> Result<Vec<CachedResponse>> {
+        (**self).get(key)
+    }
+
+    #[inline]
+    fn put(&self, key: &str, responses: Vec<CachedResponse>) -> Result<()> {
+        (**self).put(key, responses)
+    }
+
+    #[inline]
+    fn remove(&self, key: &str) -> Result<()> {
+        (**self).remove(key)
+    }
+
+    #[inline]
+    fn max_entry_size(&self) -> u64 {
+        (**self).max_entry_size()
+    }
+}
+
+/// An in-memory store, evicting the least recently used URI once it holds `capacity` of them.
+pub struct MemoryStore {
+    capacity: usize,
+    inner: Mutex<MemoryEntries>,
+}
+
+#[derive(Default)]
+struct MemoryEntries {
+    entries: HashMap<String, (u64, Vec<CachedResponse>)>,
+    clock: u64,
+}
+
+impl MemoryStore {
+    pub fn new(capacity: usize) -> Self {
+        Self {
+            capacity,
+            inner: Mutex::default(),
+        }
+    }
+
+    /// Get the number of URIs with stored responses.
+    pub fn len(&self) -> usize {
+        self.inner.lock().unwrap().entries.len()
+    }
+
+    #[inline]
+    pub fn is_empty(&self) -> bool {
+        self.len() == 0
+    }
+}
+
+impl CacheStore for MemoryStore {
+    fn get(&self, key: &str) -> Result<Vec<CachedResponse>> {
+        let mut inner = self.inner.lock().unwrap();
+        inner.clock += 1;
+        let clock = inner.clock;
+        Ok(match inner.entries.get_mut(key) {
+            Some((used, responses)) => {
+                *used = clock;
+                responses.clone()
+            }
+            None => vec![],
+        })
+    }
+
+    fn put(&self, key: &str, responses: Vec<CachedResponse>) -> Result<()> {
+        let mut inner = self.inner.lock().unwrap();
+        inner.clock += 1;
+        let clock = inner.clock;
+        inner.entries.insert(key.to_string(), (clock, responses));
+        while inner.entries.len() > self.capacity {
+            let oldest = inner
+                .entries
+                .iter()
+                .min_by_key(|(_, (used, _))| *used)
+                .map(|(key, _)| key.clone());
+            match oldest {
+                Some(oldest) => inner.entries.remove(&oldest),
+                None => break,
+            };
+        }
+        Ok(())
+    }
+
+    fn remove(&self, key: &str) -> Result<()> {
+        self.inner.lock().unwrap().entries.remove(key);
+        Ok(())
+    }
+}
+
+/// A store keeping one file per URI in a directory, relative to a directory preopened through
+/// `wasi:filesystem`.
+///
+/// The directory is created when the first response is stored. Files are replaced atomically,
+/// so several instances of a component can share the directory.
+pub struct FileStore {
+    dir: PathBuf,
+}
+
+impl FileStore {
+    pub fn new<P: Into<PathBuf>>(dir: P) -> Self {
+        Self { dir: dir.into() }
+    }
+
+    fn path(&self, key: &str) -> PathBuf {
+        let name = EntityTag::for_bytes(key.as_bytes());
+        self.dir.join(format!("{}.cache", name.tag()))
+    }
+}
+
+impl CacheStore for FileStore {
+    fn get(&self, key: &str) -> Result<Vec<CachedResponse>> {
+        match fs::read(self.path(key)) {
+            Ok(data) => decode(key, &data),
+            Err(e) if e.kind() == io::ErrorKind::NotFound => Ok(vec![]),
+            Err(e) => Err(e.into()),
+        }
+    }
+
+    fn put(&self, key: &str, responses: Vec<CachedResponse>) -> Result<()> {
+        fs::create_dir_all(&self.dir)?;
+        let path = self.path(key);
+        let tmp = path.with_extension(format!("{:x}.tmp", rand_suffix()));
+        fs::write(&tmp, encode(key, &responses))?;
+        fs::rename(&tmp, &path).inspect_err(|_| {
+            let _ = fs::remove_file(&tmp);
+        })?;
+        Ok(())
+    }
+
+    fn remove(&self, key: &str) -> Result<()> {
+        match fs::remove_file(self.path(key)) {
+            Err(e) if e.kind() != io::ErrorKind::NotFound => Err(e.into()),
+            _ => Ok(()),
+        }
+    }
+}
+
+/// A suffix making temporary file names unique across instances.
+fn rand_suffix() -> u64 {
+    use std::hash::{BuildHasher, Hasher};
+    std::collections::hash_map::RandomState::new()
+        .build_hasher()
+        .finish()
+}
+
+const MAGIC: &[u8] = b"waki-cache/1\n";
+
+fn encode(key: &str, responses: &[CachedResponse]) -> Vec<u8> {
+    fn put_u64(buf: &mut Vec<u8>, n: u64) {
+        buf.extend_from_slice(&n.to_le_bytes());
+    }
+    fn put_bytes(buf: &mut Vec<u8>, data: &[u8]) {
+        put_u64(buf, data.len() as u64);
+        buf.extend_from_slice(data);
+    }
+    fn put_time(buf: &mut Vec<u8>, time: SystemTime) {
+        let millis = time
+            .duration_since(UNIX_EPOCH)
+            .unwrap_or_default()
+            .as_millis();
+        put_u64(buf, millis as u64);
+    }
+    fn put_headers(buf: &mut Vec<u8>, headers: &HeaderMap) {
+        put_u64(buf, headers.len() as u64);
+        for (name, value) in headers {
+            put_bytes(buf, name.as_str().as_bytes());
+            put_bytes(buf, value.as_bytes());
+        }
+    }
+
+    let mut buf = MAGIC.to_vec();
+    put_bytes(&mut buf, key.as_bytes());
+    put_u64(&mut buf, responses.len() as u64);
+    for resp in responses {
+        put_u64(&mut buf, resp.status_code.into());
+        put_time(&mut buf, resp.request_time);
+        put_time(&mut buf, resp.response_time);
+        put_headers(&mut buf, &resp.headers);
+        put_headers(&mut buf, &resp.vary);
+        put_bytes(&mut buf, &resp.body);
+    }
+    buf
+}
+
+fn decode(key: &str, data: &[u8]) -> Result<Vec<CachedResponse>> {
+    struct Decoder<'a>(&'a [u8]);
+
+    impl<'a> Decoder<'a> {
+        fn take(&mut self, n: u64) -> Result<&'a [u8]> {
+            let n = usize::try_from(n)?;
+            if n > self.0.len() {
+                return Err(anyhow!("truncated cache file"));
+            }
+            let (head, tail) = self.0.split_at(n);
+            self.0 = tail;
+            Ok(head)
+        }
+
+        fn u64(&mut self) -> Result<u64> {
+            Ok(u64::from_le_bytes(self.take(8)?.try_into()?))
+        }
+
+        fn bytes(&mut self) -> Result<&'a [u8]> {
+            let len = self.u64()?;
+            self.take(len)
+        }
+
+        fn time(&mut self) -> Result<SystemTime> {
+            Ok(UNIX_EPOCH + Duration::from_millis(self.u64()?))
+        }
+
+        fn headers(&mut self) -> Result<HeaderMap> {
+            let mut headers = HeaderMap::new();
+            for _ in 0..self.u64()? {
+                let name = HeaderName::from_bytes(self.bytes()?)?;
+                headers.append(name, HeaderValue::from_bytes(self.bytes()?)?);
+            }
+            Ok(headers)
+        }
+    }
+
+    let mut decoder = Decoder(
+        data.strip_prefix(MAGIC)
+            .ok_or_else(|| anyhow!("invalid cache file"))?,
+    );
+    // a different key with the same file name
+    if decoder.bytes()? != key.as_bytes() {
+        return Ok(vec![]);
+    }
+    let mut responses = vec![];
+    for _ in 0..decoder.u64()? {
+        responses.push(CachedResponse {
+            status_code: u16::try_from(decoder.u64()?)?,
+            request_time: decoder.time()?,
+            response_time: decoder.time()?,
+            headers: decoder.headers()?,
+            vary: decoder.headers()?,
+            body: decoder.bytes()?.to_vec(),
+        });
+    }
+    Ok(responses)
+}
+
+/// The directives of `Cache-Control` headers used by the cache.
+#[derive(Debug, Default, PartialEq)]
+struct CacheControl {
+    no_store: bool,
+    no_cache: bool,
+    max_age: Option<u64>,
+    /// Whether `public`, `s-maxage` or `must-revalidate` allows storing the response to a
+    /// request with credentials.
+    shareable: bool,
+}
+
+impl CacheControl {
+    fn parse(headers: &HeaderMap) -> Self {
+        let mut cache_control = Self::default();
+        let directives = headers
+            .get_all(CACHE_CONTROL)
+            .iter()
+            .filter_map(|v| v.to_str().ok())
+            .flat_map(|v| v.split(','));
+        for directive in directives {
+            let (name, value) = match directive.split_once('=') {
+                Some((name, value)) => (name.trim(), Some(value.trim().trim_matches('"'))),
+                None => (directive.trim(), None),
+            };
+            match name.to_ascii_lowercase().as_str() {
+                "no-store" => cache_control.no_store = true,
+                "no-cache" => cache_control.no_cache = true,
+                "max-age" => cache_control.max_age = value.and_then(|v| v.parse().ok()),
+                "public" | "s-maxage" | "must-revalidate" => cache_control.shareable = true,
+                _ => {}
+            }
+        }
+        cache_control
+    }
+}
+
+fn header_date(headers: &HeaderMap, name: HeaderName) -> Option<SystemTime> {
+    httpdate::parse_http_date(headers.get(name)?.to_str().ok()?).ok()
+}
+
+fn vary_names(headers: &HeaderMap) -> Vec<String> {
+    headers
+        .get_all(VARY)
+        .iter()
+        .filter_map(|v| v.to_str().ok())
+        .flat_map(|v| v.split(','))
+        .map(|name| name.trim().to_ascii_lowercase())
+        .filter(|name| !name.is_empty())
+        .collect()
+}
+
+/// Build the key of the responses to a URI.
+fn cache_key(uri: &Parts) -> String {
+    format!(
+        "{}://{}{}",
+        uri.scheme.as_ref().map_or("http", |s| s.as_str()),
+        uri.authority.as_ref().map_or("", |a| a.as_str()),
+        uri.path_and_query.as_ref().map_or("/", |p| p.as_str()),
+    )
+}
+
+/// The outcome of looking a request up in the cache.
+pub(crate) enum Lookup {
+    /// A fresh stored response.
+    Hit(Response),
+    /// The request must be sent, then its response passed to [`Pending::complete`].
+    Miss(Box<Request>, Pending),
+}
+
+/// What is needed to store the response of a request missing the cache.
+pub(crate) struct Pending {
+    store: Arc<dyn CacheStore>,
+    key: String,
+    mode: Mode,
+    request_time: SystemTime,
+}
+
+enum Mode {
+    /// The response is passed through.
+    Bypass,
+    /// The stored responses are removed once the response is successful.
+    Invalidate,
+    /// The response may be stored, `selected` being the position of the stored response being
+    /// revalidated.
+    Store {
+        request_headers: HeaderMap,
+        responses: Vec<CachedResponse>,
+        selected: Option<usize>,
+    },
+}
+
+/// A response being stored while its body is read.
+struct Entry {
+    store: Arc<dyn CacheStore>,
+    key: String,
+    request_headers: HeaderMap,
+    responses: Vec<CachedResponse>,
+    cached: CachedResponse,
+    max_size: u64,
+}
+
+impl Entry {
+    fn put(mut self) {
+        match self
+            .responses
+            .iter()
+            .position(|r| r.matches(&self.request_headers))
+        {
+            Some(i) => self.responses[i] = self.cached,
+            None => self.responses.push(self.cached),
+        }
+        let _ = self.store.put(&self.key, self.responses);
+    }
+}
+
+impl Tee for Entry {
+    fn write(&mut self, data: &[u8]) -> bool {
+        self.cached.body.extend_from_slice(data);
+        self.cached.body.len() as u64 <= self.max_size
+    }
+
+    fn end(self: Box<Self>) {
+        self.put();
+    }
+}
+
+/// Look the request up in `store`, adding the validators of a stale response to the request.
+pub(crate) fn lookup(store: Arc<dyn CacheStore>, mut req: Request) -> Lookup {
+    let key = cache_key(&req.uri);
+    let request_time = SystemTime::now();
+    let pending = |store, key, mode| Pending {
+        store,
+        key,
+        mode,
+        request_time,
+    };
+
+    match req.method {
+        Method::Get => {}
+        Method::Head | Method::Options | Method::Trace => {
+            return Lookup::Miss(Box::new(req), pending(store, key, Mode::Bypass))
+        }
+        _ => return Lookup::Miss(Box::new(req), pending(store, key, Mode::Invalidate)),
+    }
+    // the caller handles conditional and range requests
+    let conditional = [
+        IF_MATCH,
+        IF_NONE_MATCH,
+        IF_MODIFIED_SINCE,
+        IF_UNMODIFIED_SINCE,
+        IF_RANGE,
+        RANGE,
+    ]
+    .iter()
+    .any(|name| req.headers.contains_key(name));
+    let cache_control = CacheControl::parse(&req.headers);
+    if conditional || cache_control.no_store {
+        return Lookup::Miss(Box::new(req), pending(store, key, Mode::Bypass));
+    }
+
+    let responses = store.get(&key).unwrap_or_default();
+    let selected = responses.iter().position(|resp| resp.matches(&req.headers));
+    let request_headers = req.headers.clone();
+    if let Some(cached) = selected.map(|i| &responses[i]) {
+        let age = cached.age(SystemTime::now());
+        let fresh = !cache_control.no_cache
+            && age < cached.freshness_lifetime()
+            && cache_control
+                .max_age
+                .is_none_or(|max_age| age.as_secs() <= max_age);
+        if fresh {
+            return Lookup::Hit(cached.to_response(age));
+        }
+        if let Some(etag) = cached.headers.get(ETAG) {
+            req.headers.insert(IF_NONE_MATCH, etag.clone());
+        }
+        if let Some(last_modified) = cached.headers.get(LAST_MODIFIED) {
+            req.headers.insert(IF_MODIFIED_SINCE, last_modified.clone());
+        }
+    }
+    let mode = Mode::Store {
+        request_headers,
+        responses,
+        selected,
+    };
+    Lookup::Miss(Box::new(req), pending(store, key, mode))
+}
+
+impl Pending {
+    /// Store the response if possible, returning the response to hand to the caller.
+    pub(crate) fn complete(self, resp: Result<Response>) -> Result<Response> {
+        let mut resp = resp?;
+        let (request_headers, mut responses, selected) = match self.mode {
+            Mode::Bypass => return Ok(resp),
+            Mode::Invalidate => {
+                if resp.status_code < 400 {
+                    let _ = self.store.remove(&self.key);
+                }
+                return Ok(resp);
+            }
+            Mode::Store {
+                request_headers,
+                responses,
+                selected,
+            } => (request_headers, responses, selected),
+        };
+        let response_time = SystemTime::now();
+
+        if resp.status_code == 304 {
+            let Some(i) = selected else {
+                return Ok(resp);
+            };
+            // update the stored response with the headers of the 304 response
+            let cached = &mut responses[i];
+            for name in resp.headers.keys() {
+                if name != CONTENT_LENGTH {
+                    cached.headers.remove(name);
+                }
+            }
+            for (name, value) in resp.headers.iter() {
+                if name != CONTENT_LENGTH {
+                    cached.headers.append(name, value.clone());
+                }
+            }
+            cached.request_time = self.request_time;
+            cached.response_time = response_time;
+            let resp = cached.to_response(Duration::ZERO);
+            let _ = self.store.put(&self.key, responses);
+            return Ok(resp);
+        }
+
+        let cache_control = CacheControl::parse(&resp.headers);
+        let vary = vary_names(&resp.headers);
+        let max_size = self.store.max_entry_size();
+        let storable = CACHEABLE_STATUS_CODES.contains(&resp.status_code)
+            && !cache_control.no_store
+            && !vary.iter().any(|name| name == "*")
+            && (cache_control.max_age.is_some()
+                || [EXPIRES, ETAG, LAST_MODIFIED]
+                    .iter()
+                    .any(|name| resp.headers.contains_key(name)))
+            && (cache_control.shareable || !request_headers.contains_key(AUTHORIZATION))
+            && resp.content_length().is_none_or(|len| len <= max_size);
+        if !storable {
+            return Ok(resp);
+        }
+
+        let mut vary_headers = HeaderMap::new();
+        for name in vary {
+            for value in request_headers.get_all(&name) {
+                vary_headers.append(HeaderName::from_bytes(name.as_bytes())?, value.clone());
+            }
+        }
+        let mut entry = Entry {
+            store: self.store,
+            key: self.key,
+            request_headers,
+            responses,
+            cached: CachedResponse {
+                status_code: resp.status_code,
+                headers: resp.headers.clone(),
+                body: vec![],
+                vary: vary_headers,
+                request_time: self.request_time,
+                response_time,
+            },
+            max_size,
+        };
+        match &mut resp.body {
+            Body::Bytes(data) if data.len() as u64 <= max_size => {
+                entry.cached.body = data.clone();
+                entry.put();
+            }
+            Body::Stream(s) => s.tee(Box::new(entry)),
+            _ => {}
+        }
+        Ok(resp)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn cached(headers: &[(HeaderName, &str)]) -> CachedResponse {
+        let now = SystemTime::now();
+        let mut map = HeaderMap::new();
+        for (name, value) in headers {
+            map.append(name, value.parse().unwrap());
+        }
+        CachedResponse {
+            status_code: 200,
+            headers: map,
+            body: b"hello".to_vec(),
+            vary: HeaderMap::new(),
+            request_time: now,
+            response_time: now,
+        }
+    }
+
+    #[test]
+    fn test_cache_control() {
+        let mut headers = HeaderMap::new();
+        headers.append(CACHE_CONTROL, "public, max-age=\"60\"".parse().unwrap());
+        headers.append(CACHE_CONTROL, "No-Cache".parse().unwrap());
+        assert_eq!(
+            CacheControl::parse(&headers),
+            CacheControl {
+                no_store: false,
+                no_cache: true,
+                max_age: Some(60),
+                shareable: true,
+            }
+        );
+    }
+
+    #[test]
+    fn test_freshness() {
+        let resp = cached(&[(CACHE_CONTROL, "max-age=60")]);
+        assert_eq!(resp.freshness_lifetime(), Duration::from_secs(60));
+
+        let date = httpdate::fmt_http_date(SystemTime::now());
+        let expires = httpdate::fmt_http_date(SystemTime::now() + Duration::from_secs(30));
+        let resp = cached(&[(DATE, &date), (EXPIRES, &expires)]);
+        assert_eq!(resp.freshness_lifetime(), Duration::from_secs(30));
+        let resp = cached(&[(DATE, &date), (EXPIRES, "0")]);
+        assert_eq!(resp.freshness_lifetime(), Duration::ZERO);
+
+        let last_modified = httpdate::fmt_http_date(SystemTime::now() - Duration::from_secs(1000));
+        let resp = cached(&[(DATE, &date), (LAST_MODIFIED, &last_modified)]);
+        assert_eq!(resp.freshness_lifetime(), Duration::from_secs(100));
+
+        let mut resp = cached(&[(AGE, "10")]);
+        resp.response_time -= Duration::from_secs(5);
+        assert_eq!(resp.age(resp.response_time).as_secs(), 10);
+        assert_eq!(resp.age(SystemTime::now()).as_secs(), 15);
+    }
+
+    #[test]
+    fn test_vary() {
+        let mut resp = cached(&[(VARY, "Accept-Encoding, accept")]);
+        resp.vary
+            .insert(crate::header::ACCEPT, "text/html".parse().unwrap());
+
+        let mut headers = HeaderMap::new();
+        headers.insert(crate::header::ACCEPT, "text/html".parse().unwrap());
+        assert!(resp.matches(&headers));
+        headers.insert(crate::header::ACCEPT_ENCODING, "gzip".parse().unwrap());
+        assert!(!resp.matches(&headers));
+    }
+
+    #[test]
+    fn test_memory_store() -> Result<()> {
+        let store = MemoryStore::new(2);
+        store.put("a", vec![cached(&[])])?;
+        store.put("b", vec![])?;
+        assert_eq!(store.get("a")?.len(), 1);
+        store.put("c", vec![])?;
+        // "b" is the least recently used
+        assert_eq!(store.len(), 2);
+        assert_eq!(store.get("b")?.len(), 0);
+        assert_eq!(store.get("a")?.len(), 1);
+        store.remove("a")?;
+        assert_eq!(store.get("a")?.len(), 0);
+        Ok(())
+    }
+
+    #[test]
+    fn test_file_store() -> Result<()> {
+        let dir = std::env::temp_dir().join(format!("waki-cache-{:x}", rand_suffix()));
+        let store = FileStore::new(&dir);
+        let mut resp = cached(&[(ETAG, "\"1\""), (VARY, "accept")]);
+        resp.vary
+            .insert(crate::header::ACCEPT, "*/*".parse().unwrap());
+        // the stored times have a precision of one millisecond
+        resp.request_time = UNIX_EPOCH + Duration::from_millis(1_700_000_000_123);
+        resp.response_time = resp.request_time;
+
+        assert_eq!(store.get("https://example.com/")?, vec![]);
+        store.put("https://example.com/", vec![resp.clone()])?;
+        assert_eq!(store.get("https://example.com/")?, vec![resp]);
+        assert_eq!(store.get("https://example.com/other")?, vec![]);
+        store.remove("https://example.com/")?;
+        assert_eq!(store.get("https://example.com/")?, vec![]);
+
+        fs::remove_dir_all(dir)?;
+        Ok(())
+    }
+
+    fn send(store: &Arc<MemoryStore>, req: Request, cache_control: &str, body: &[u8]) -> Response {
+        let resp = Response::builder()
+            .status_code(if matches!(req.method, Method::Post) {
+                204
+            } else {
+                200
+            })
+            .header(CACHE_CONTROL, cache_control)
+            .body(body)
+            .build()
+            .unwrap();
+        match lookup(store.clone(), req) {
+            Lookup::Hit(resp) => resp,
+            Lookup::Miss(_, pending) => pending.complete(Ok(resp)).unwrap(),
+        }
+    }
+
+    fn request(method: Method, headers: &[(HeaderName, &str)]) -> Request {
+        let uri = "https://example.com/data".parse::<http::Uri>().unwrap();
+        let mut req = Request::new(method, uri.into_parts());
+        for (name, value) in headers {
+            req.headers.insert(name, value.parse().unwrap());
+        }
+        req
+    }
+
+    #[test]
+    fn test_lookup() {
+        let store = Arc::new(MemoryStore::new(16));
+        let resp = send(&store, request(Method::Get, &[]), "max-age=60", b"hello");
+        assert!(!resp.headers.contains_key(AGE));
+        assert_eq!(store.len(), 1);
+        let resp = send(&store, request(Method::Get, &[]), "max-age=60", b"world");
+        assert_eq!(resp.headers[AGE], "0");
+        assert_eq!(resp.body.as_bytes(), Some(&b"hello"[..]));
+
+        // a successful unsafe request invalidates the stored responses
+        send(&store, request(Method::Post, &[]), "no-store", b"");
+        assert!(store.is_empty());
+    }
+
+    #[test]
+    fn test_lookup_not_stored() {
+        let store = Arc::new(MemoryStore::new(16));
+        let large = vec![0; DEFAULT_MAX_ENTRY_SIZE as usize + 1];
+        send(&store, request(Method::Get, &[]), "max-age=60", &large);
+        assert!(store.is_empty());
+        send(&store, request(Method::Get, &[]), "no-store", b"hello");
+        assert!(store.is_empty());
+
+        // responses to requests with credentials must be explicitly shareable
+        let authorized = [(AUTHORIZATION, "Bearer token")];
+        send(
+            &store,
+            request(Method::Get, &authorized),
+            "max-age=60",
+            b"hello",
+        );
+        assert!(store.is_empty());
+        send(
+            &store,
+            request(Method::Get, &authorized),
+            "public, max-age=60",
+            b"hello",
+        );
+        assert_eq!(store.len(), 1);
+    }
+}
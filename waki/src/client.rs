@@ -1,7 +1,26 @@
-use crate::{Method, RequestBuilder};
+use crate::{
+    auth::TokenProvider,
+    proxy::{forward_request, strip_hop_by_hop_headers},
+    Download, Join, Method, Request, RequestBuilder, Response,
+};
 
-#[derive(Default)]
-pub struct Client {}
+#[cfg(feature = "cache")]
+use crate::cache::CacheStore;
+#[cfg(feature = "signing")]
+use crate::sign::Signer;
+
+use anyhow::Result;
+use std::io::Write;
+use std::sync::Arc;
+
+#[derive(Default, Clone)]
+pub struct Client {
+    token_provider: Option<Arc<dyn TokenProvider>>,
+    #[cfg(feature = "cache")]
+    cache: Option<Arc<dyn CacheStore>>,
+    #[cfg(feature = "signing")]
+    signer: Option<Arc<dyn Signer>>,
+}
 
 impl Client {
     #[inline]
@@ -9,6 +28,49 @@ impl Client {
         Default::default()
     }
 
+    /// Authenticate the requests of this client with bearer tokens from `provider`.
+    ///
+    /// The token is fetched when each request is sent, unless the request already has an
+    /// `Authorization` header.
+    ///
+    /// ```
+    /// # use waki::Client;
+    /// # fn run() {
+    /// let client = Client::new().token_provider(|| Ok(std::env::var("API_TOKEN")?));
+    /// # }
+    /// ```
+    #[inline]
+    pub fn token_provider<P: TokenProvider + 'static>(mut self, provider: P) -> Self {
+        self.token_provider = Some(Arc::new(provider));
+        self
+    }
+
+    /// Cache the responses to the requests of this client in `store`, see [`crate::cache`].
+    ///
+    /// The cache is only used by `send` and `send_async`.
+    ///
+    /// # Optional
+    ///
+    /// This requires the `cache` feature enabled.
+    #[cfg(feature = "cache")]
+    #[inline]
+    pub fn cache<S: CacheStore + 'static>(mut self, store: S) -> Self {
+        self.cache = Some(Arc::new(store));
+        self
+    }
+
+    /// Sign the requests of this client with `signer`, see [`crate::sign`].
+    ///
+    /// # Optional
+    ///
+    /// This requires the `signing` feature enabled.
+    #[cfg(feature = "signing")]
+    #[inline]
+    pub fn signer<S: Signer + 'static>(mut self, signer: S) -> Self {
+        self.signer = Some(Arc::new(signer));
+        self
+    }
+
     #[inline]
     pub fn get(&self, url: &str) -> RequestBuilder {
         self.request(Method::Get, url)
@@ -41,6 +103,73 @@ impl Client {
 
     #[inline]
     pub fn request(&self, method: Method, url: &str) -> RequestBuilder {
-        RequestBuilder::new(method, url)
+        let mut builder = RequestBuilder::new(method, url);
+        if let Ok(ref mut req) = builder.inner {
+            self.configure(req);
+        }
+        builder
+    }
+
+    /// Apply the token provider, cache and signer of this client to `req`.
+    fn configure(&self, req: &mut Request) {
+        req.token_provider.clone_from(&self.token_provider);
+        #[cfg(feature = "cache")]
+        req.cache.clone_from(&self.cache);
+        #[cfg(feature = "signing")]
+        req.signer.clone_from(&self.signer);
+    }
+
+    /// Send several requests concurrently, yielding each response with its position as soon
+    /// as it completes, see [`Join`].
+    #[inline]
+    pub fn send_all<I: IntoIterator<Item = RequestBuilder>>(&self, requests: I) -> Join {
+        Join::start(requests)
+    }
+
+    /// Download `url` to `writer`, resuming after transient failures, see [`Download`].
+    ///
+    /// ```
+    /// # use anyhow::Result;
+    /// # use waki::Client;
+    /// # fn run() -> Result<()> {
+    /// let mut file = std::fs::File::create("archive.tar.gz")?;
+    /// let len = Client::new()
+    ///     .download_to("https://example.com/archive.tar.gz", &mut file)
+    ///     .max_retries(5)
+    ///     .send()?;
+    /// # Ok(())
+    /// # }
+    /// ```
+    #[inline]
+    pub fn download_to<W: Write>(&self, url: &str, writer: W) -> Download<'_, W> {
+        Download::new(self, url, writer)
+    }
+
+    /// Forward an incoming request to the upstream `url`, returning the upstream response.
+    ///
+    /// The scheme and authority are taken from `url`, its path is prepended to the path of the
+    /// request and the query string is kept. Hop-by-hop headers are stripped in both directions
+    /// and the `Forwarded`, `X-Forwarded-Host` and `X-Forwarded-Proto` headers are added. The
+    /// token provider, cache and signer of this client apply to the forwarded request.
+    ///
+    /// Neither body is buffered: the incoming body is streamed to the upstream, and returning
+    /// the response from a handler streams the upstream body back to the client.
+    ///
+    /// ```
+    /// use waki::{handler, Client, ErrorCode, Request, Response};
+    ///
+    /// #[handler]
+    /// fn gateway(req: Request) -> Result<Response, ErrorCode> {
+    ///     Client::new()
+    ///         .forward(req, "http://backend:8080")
+    ///         .map_err(|e| ErrorCode::InternalError(Some(e.to_string())))
+    /// }
+    /// ```
+    pub fn forward(&self, req: Request, url: &str) -> Result<Response> {
+        let mut req = forward_request(req, url)?;
+        self.configure(&mut req);
+        let mut resp = req.send()?;
+        strip_hop_by_hop_headers(&mut resp.headers);
+        Ok(resp)
     }
 }
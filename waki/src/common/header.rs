@@ -21,10 +21,20 @@ macro_rules! impl_header {
 
 impl_header!(IncomingRequest, IncomingResponse);
 
+// the values of a header are kept in order, following the `append` order of the `HeaderMap`
 impl TryFrom<HeaderMap> for Headers {
     type Error = HeaderError;
 
+    #[inline]
     fn try_from(headers: HeaderMap) -> Result<Self, Self::Error> {
+        (&headers).try_into()
+    }
+}
+
+impl TryFrom<&HeaderMap> for Headers {
+    type Error = HeaderError;
+
+    fn try_from(headers: &HeaderMap) -> Result<Self, Self::Error> {
         let entries = headers
             .iter()
             .map(|(k, v)| (k.to_string(), v.as_bytes().into()))
@@ -1,3 +1,5 @@
 mod header;
+mod method;
 mod request_and_response;
 mod scheme;
+mod text;
@@ -1,16 +1,29 @@
+#[cfg(feature = "async")]
+use crate::body::BodyWriter;
+#[cfg(feature = "json")]
+use crate::json::{self, JsonArrayStream, JsonLines, JsonLinesReader, NDJSON};
 #[cfg(feature = "multipart")]
-use crate::multipart::{parser::parse, Form, Part, StreamingForm};
+use crate::multipart::{parser::parse, Form, Multipart, MultipartReader, Part, StreamingForm};
 use crate::{
-    body::Body,
-    header::{AsHeaderName, HeaderMap, HeaderValue, IntoHeaderName, CONTENT_TYPE},
+    body::{Body, BodyReader},
+    header::{
+        AsHeaderName, HeaderMap, HeaderValue, IntoHeaderName, ACCEPT, AUTHORIZATION,
+        CONTENT_LENGTH, CONTENT_TYPE, DATE, IF_MATCH, IF_MODIFIED_SINCE, IF_NONE_MATCH,
+        IF_UNMODIFIED_SINCE,
+    },
+    typed_header::{parse_quality_list, Authorization, IfMatch, IfNoneMatch, QualityItem},
     Request, RequestBuilder, Response, ResponseBuilder,
 };
+
+use super::text;
 use anyhow::{anyhow, Error, Result};
-#[cfg(feature = "json")]
+use mime::Mime;
+#[cfg(any(feature = "json", feature = "cbor", feature = "msgpack"))]
 use serde::Serialize;
 use std::borrow::Borrow;
 use std::collections::HashMap;
 use std::io::Read;
+use std::time::SystemTime;
 
 macro_rules! impl_common_get_methods {
     ($($t:ty),+ $(,)?) => ($(
@@ -27,6 +40,70 @@ macro_rules! impl_common_get_methods {
                 &self.headers
             }
 
+            /// Get a mutable reference to the headers.
+            #[inline]
+            pub fn headers_mut(&mut self) -> &mut HeaderMap {
+                &mut self.headers
+            }
+
+            /// Get the `Content-Type` header, `None` if it is missing or invalid.
+            pub fn content_type(&self) -> Option<Mime> {
+                self.headers.get(CONTENT_TYPE)?.to_str().ok()?.parse().ok()
+            }
+
+            /// Get the `Content-Length` header, `None` if it is missing or invalid.
+            pub fn content_length(&self) -> Option<u64> {
+                self.headers.get(CONTENT_LENGTH)?.to_str().ok()?.trim().parse().ok()
+            }
+
+            /// Get the media ranges of the `Accept` header, sorted by descending quality.
+            ///
+            /// Invalid media ranges are skipped.
+            ///
+            /// ```
+            /// # use waki::Request;
+            /// # fn run(req: Request) {
+            /// for range in req.accept() {
+            ///     println!("{} (q={})", range.item, range.quality);
+            /// }
+            /// # }
+            /// ```
+            pub fn accept(&self) -> Vec<QualityItem<Mime>> {
+                parse_quality_list(self.headers.get_all(ACCEPT))
+            }
+
+            /// Get the credentials of the `Authorization` header, `None` if it is missing or
+            /// invalid.
+            pub fn authorization(&self) -> Option<Authorization> {
+                self.headers.get(AUTHORIZATION)?.to_str().ok()?.parse().ok()
+            }
+
+            /// Get the `If-None-Match` header, `None` if it is missing or invalid.
+            pub fn if_none_match(&self) -> Option<IfNoneMatch> {
+                IfNoneMatch::from_values(self.headers.get_all(IF_NONE_MATCH)).ok()?
+            }
+
+            /// Get the `If-Match` header, `None` if it is missing or invalid.
+            pub fn if_match(&self) -> Option<IfMatch> {
+                IfMatch::from_values(self.headers.get_all(IF_MATCH)).ok()?
+            }
+
+            /// Get the `If-Modified-Since` header, `None` if it is missing or invalid.
+            pub fn if_modified_since(&self) -> Option<SystemTime> {
+                httpdate::parse_http_date(self.headers.get(IF_MODIFIED_SINCE)?.to_str().ok()?).ok()
+            }
+
+            /// Get the `If-Unmodified-Since` header, `None` if it is missing or invalid.
+            pub fn if_unmodified_since(&self) -> Option<SystemTime> {
+                httpdate::parse_http_date(self.headers.get(IF_UNMODIFIED_SINCE)?.to_str().ok()?)
+                    .ok()
+            }
+
+            /// Get the `Date` header, `None` if it is missing or invalid.
+            pub fn date(&self) -> Option<SystemTime> {
+                httpdate::parse_http_date(self.headers.get(DATE)?.to_str().ok()?).ok()
+            }
+
             /// Get a chunk of the body.
             ///
             /// It will block until at least one byte can be read or the stream is closed.
@@ -38,16 +115,150 @@ macro_rules! impl_common_get_methods {
                 self.body.chunk(len)
             }
 
+            /// Get a chunk of the body asynchronously.
+            ///
+            /// It will wait until at least one byte can be read or the stream is closed, letting
+            /// other tasks of the executor in [`crate::rt`] run in the meantime.
+            ///
+            /// # Optional
+            ///
+            /// This requires the `async` feature enabled.
+            #[cfg(feature = "async")]
+            #[inline]
+            pub async fn chunk_async(&self, len: u64) -> Result<Option<Vec<u8>>> {
+                self.body.chunk_async(len).await
+            }
+
+            /// Read a chunk of at most `buf.len()` bytes of the body into `buf`, returning the
+            /// number of bytes read.
+            ///
+            /// It will block until at least one byte can be read, and returns 0 once the stream
+            /// is closed.
+            ///
+            /// NOTE: This method is only for incoming requests/responses, if you call it on an
+            /// outgoing request/response it will always return 0.
+            #[inline]
+            pub fn read_chunk(&self, buf: &mut [u8]) -> Result<usize> {
+                self.body.read_chunk(buf)
+            }
+
             /// Get the full body.
             ///
-            /// It will block until the stream is closed.
+            /// It will block until the stream is closed, and fails with
+            /// [`BodyLimitExceeded`](crate::BodyLimitExceeded) if an incoming body is larger than
+            /// its limit, see [`Self::set_body_limit`].
             #[inline]
             pub fn body(self) -> Result<Vec<u8>> {
                 self.body.bytes()
             }
 
+            /// Convert the body into a reader, implementing [`std::io::Read`] and
+            /// [`std::io::BufRead`].
+            ///
+            /// ```
+            /// # use anyhow::Result;
+            /// # use std::io::BufRead;
+            /// # use waki::Client;
+            /// # fn run() -> Result<()> {
+            /// let resp = Client::new().get("https://httpbin.org/stream/5").send()?;
+            /// for line in resp.into_reader().lines() {
+            ///     println!("{}", line?);
+            /// }
+            /// # Ok(())
+            /// # }
+            /// ```
+            #[inline]
+            pub fn into_reader(self) -> BodyReader {
+                BodyReader::new(self.body)
+            }
+
+            /// Read the body into memory, so that it can be read several times with the
+            /// borrowing methods such as [`Self::bytes_ref`], [`Self::text`] or
+            /// [`Self::json_ref`].
+            ///
+            /// Reading fails if an incoming body is larger than its limit, see
+            /// [`Self::set_body_limit`], and every later read of the body then fails too. The
+            /// consuming methods such as `body()` or `json()` still work after a successful read.
+            ///
+            /// ```
+            /// # use anyhow::Result;
+            /// # use waki::Client;
+            /// # fn run() -> Result<()> {
+            /// let mut resp = Client::new().get("https://httpbin.org/get").send()?;
+            /// resp.buffer()?;
+            /// println!("{}: {}", resp.status_code(), resp.text()?);
+            /// # Ok(())
+            /// # }
+            /// ```
+            #[inline]
+            pub fn buffer(&mut self) -> Result<()> {
+                self.body.buffer(None).map(|_| ())
+            }
+
+            /// Read the body into memory, failing if an incoming body is larger than `limit`
+            /// bytes.
+            #[inline]
+            pub fn buffer_with_limit(&mut self, limit: u64) -> Result<()> {
+                self.body.buffer(Some(limit)).map(|_| ())
+            }
+
+            /// Set the maximum size of an incoming body read into memory, overriding the
+            /// default set with [`set_default_body_limit`](crate::set_default_body_limit).
+            ///
+            /// `Content-Length` is checked before reading, so that too large bodies are rejected
+            /// early. It has no effect on outgoing bodies.
+            #[inline]
+            pub fn set_body_limit(&mut self, limit: u64) {
+                self.body.set_limit(limit)
+            }
+
+            /// Get the body if it is held in memory, `None` if it has not been buffered yet.
+            #[inline]
+            pub fn body_ref(&self) -> Option<&[u8]> {
+                self.body.as_bytes()
+            }
+
+            /// Get the full body without consuming it, buffering it first if needed.
+            #[inline]
+            pub fn bytes_ref(&mut self) -> Result<&[u8]> {
+                self.body.buffer(None)
+            }
+
+            /// Get the body as text without consuming it, buffering it first if needed.
+            ///
+            /// The body is decoded with the `charset` parameter of the `Content-Type` header,
+            /// UTF-8 if there is none, unless it starts with a byte order mark. Charsets other
+            /// than UTF-8 require the `encoding` feature. Malformed text is an error, see
+            /// [`Self::text_lossy`].
+            pub fn text(&mut self) -> Result<String> {
+                let content_type = self.content_type();
+                text::decode(self.bytes_ref()?, content_type.as_ref(), false)
+            }
+
+            /// Get the body as text like [`Self::text`], replacing malformed sequences with
+            /// `U+FFFD` and decoding unknown charsets as UTF-8.
+            pub fn text_lossy(&mut self) -> Result<String> {
+                let content_type = self.content_type();
+                text::decode(self.bytes_ref()?, content_type.as_ref(), true)
+            }
+
+            /// Deserialize the body as JSON without consuming it, buffering it first if needed.
+            ///
+            /// Unlike `json()` the value may borrow from the body.
+            ///
+            /// # Optional
+            ///
+            /// This requires the `json` feature enabled.
+            #[cfg(feature = "json")]
+            pub fn json_ref<'a, T: serde::Deserialize<'a>>(&'a mut self) -> Result<T> {
+                Ok(serde_json::from_slice(self.bytes_ref()?)?)
+            }
+
             /// Deserialize the body as JSON.
             ///
+            /// An incoming body is deserialized as it is received, without holding its raw
+            /// bytes in memory, and is subject to its limit, see [`Self::set_body_limit`].
+            ///
             /// # Optional
             ///
             /// This requires the `json` feature enabled.
@@ -70,7 +281,113 @@ macro_rules! impl_common_get_methods {
             /// ```
             #[cfg(feature = "json")]
             pub fn json<T: serde::de::DeserializeOwned>(self) -> Result<T> {
-                Ok(serde_json::from_slice(self.body()?.as_ref())?)
+                json::from_body(self.body)
+            }
+
+            /// Deserialize the body as newline delimited JSON (NDJSON or JSON Lines), one record
+            /// at a time as the body is received.
+            ///
+            /// # Optional
+            ///
+            /// This requires the `json` feature enabled.
+            ///
+            /// ```
+            /// # use anyhow::Result;
+            /// # use serde::Deserialize;
+            /// # use waki::Client;
+            /// # fn run() -> Result<()> {
+            /// #[derive(Deserialize)]
+            /// struct Event {
+            ///     id: u64,
+            /// }
+            ///
+            /// let resp = Client::new().get("https://example.com/events").send()?;
+            /// for event in resp.json_lines::<Event>() {
+            ///     println!("{}", event?.id);
+            /// }
+            /// # Ok(())
+            /// # }
+            /// ```
+            #[cfg(feature = "json")]
+            #[inline]
+            pub fn json_lines<T: serde::de::DeserializeOwned>(self) -> JsonLines<T> {
+                JsonLines::new(self.body)
+            }
+
+            /// Deserialize the elements of a body made of a top-level JSON array, one at a time
+            /// as the body is received.
+            ///
+            /// # Optional
+            ///
+            /// This requires the `json` feature enabled.
+            ///
+            /// ```
+            /// # use anyhow::Result;
+            /// # use serde::Deserialize;
+            /// # use waki::Client;
+            /// # fn run() -> Result<()> {
+            /// #[derive(Deserialize)]
+            /// struct Item {
+            ///     name: String,
+            /// }
+            ///
+            /// let resp = Client::new().get("https://example.com/items").send()?;
+            /// for item in resp.json_array_stream::<Item>() {
+            ///     println!("{}", item?.name);
+            /// }
+            /// # Ok(())
+            /// # }
+            /// ```
+            #[cfg(feature = "json")]
+            #[inline]
+            pub fn json_array_stream<T: serde::de::DeserializeOwned>(self) -> JsonArrayStream<T> {
+                JsonArrayStream::new(self.body)
+            }
+
+            /// Deserialize the body as CBOR.
+            ///
+            /// # Optional
+            ///
+            /// This requires the `cbor` feature enabled.
+            #[cfg(feature = "cbor")]
+            pub fn cbor<T: serde::de::DeserializeOwned>(self) -> Result<T> {
+                Ok(ciborium::from_reader(self.body()?.as_slice())?)
+            }
+
+            /// Deserialize the body as MessagePack.
+            ///
+            /// # Optional
+            ///
+            /// This requires the `msgpack` feature enabled.
+            #[cfg(feature = "msgpack")]
+            pub fn msgpack<T: serde::de::DeserializeOwned>(self) -> Result<T> {
+                Ok(rmp_serde::from_slice(&self.body()?)?)
+            }
+
+            /// Decode the body as a Protocol Buffers message.
+            ///
+            /// # Optional
+            ///
+            /// This requires the `protobuf` feature enabled.
+            ///
+            /// ```
+            /// # use anyhow::Result;
+            /// # use waki::Response;
+            /// # fn run() -> Result<()> {
+            /// # let r = Response::new();
+            /// #[derive(Clone, PartialEq, prost::Message)]
+            /// struct Data {
+            ///     #[prost(string, tag = "1")]
+            ///     name: String,
+            /// }
+            ///
+            /// let data = r.protobuf::<Data>()?;
+            /// # Ok(())
+            /// # }
+            /// ```
+            #[cfg(feature = "protobuf")]
+            pub fn protobuf<T: prost::Message + Default>(self) -> Result<T> {
+                Ok(T::decode(self.body()?.as_slice())?)
             }
 
             /// Parse the body as form data.
@@ -88,21 +405,70 @@ macro_rules! impl_common_get_methods {
                 match self.headers.get(CONTENT_TYPE) {
                     Some(header) => {
                         let mime = header.to_str()?.parse::<mime::Mime>()?;
-                        let boundary = match mime.get_param(mime::BOUNDARY) {
-                            Some(v) => v.as_str(),
-                            None => {
-                                return Err(anyhow!(
-                                    "unable to find the boundary value in the Content-Type header"
-                                ))
-                            }
-                        };
-                        parse(self.body()?.as_ref(), boundary)
+                        parse(&mime, self.body()?.as_ref())
+                    }
+                    None => Err(anyhow!(
+                        "parse body as multipart failed, unable to find the Content-Type header"
+                    )),
+                }
+            }
+
+            /// Parse the body as a multipart body of any subtype, such as `multipart/mixed`,
+            /// `multipart/related` or `multipart/byteranges`.
+            ///
+            /// Nested multipart parts are parsed recursively.
+            ///
+            /// # Optional
+            ///
+            /// This requires the `multipart` feature enabled.
+            #[cfg(feature = "multipart")]
+            pub fn multipart_body(self) -> Result<Multipart> {
+                match self.headers.get(CONTENT_TYPE) {
+                    Some(header) => {
+                        let content_type = header.to_str()?.to_string();
+                        Multipart::parse(&content_type, self.body()?.as_ref())
                     }
                     None => Err(anyhow!(
                         "parse body as multipart failed, unable to find the Content-Type header"
                     )),
                 }
             }
+
+            /// Stream the parts of a multipart body of any subtype as they arrive.
+            ///
+            /// Unlike `multipart()` and `multipart_body()` which load the whole body into
+            /// memory, each part exposes its headers and a reader over its content.
+            ///
+            /// # Optional
+            ///
+            /// This requires the `multipart` feature enabled.
+            ///
+            /// ```
+            /// # use anyhow::Result;
+            /// # use waki::Client;
+            /// # fn run() -> Result<()> {
+            /// let resp = Client::new()
+            ///     .get("https://example.com/file")
+            ///     .header("Range", "bytes=0-99,200-299")
+            ///     .send()?;
+            ///
+            /// let mut parts = resp.multipart_reader()?;
+            /// while let Some(mut part) = parts.next_part()? {
+            ///     let range = part.content_range();
+            ///     std::io::copy(&mut part, &mut std::io::sink())?;
+            /// }
+            /// # Ok(())
+            /// # }
+            /// ```
+            #[cfg(feature = "multipart")]
+            pub fn multipart_reader(self) -> Result<MultipartReader> {
+                match self.headers.get(CONTENT_TYPE) {
+                    Some(header) => MultipartReader::new(header.to_str()?.parse()?, self.body),
+                    None => Err(anyhow!(
+                        "parse body as multipart failed, unable to find the Content-Type header"
+                    )),
+                }
+            }
         }
     )+)
 }
@@ -112,7 +478,7 @@ impl_common_get_methods!(Request, Response);
 macro_rules! impl_common_set_methods {
     ($($t:ty),+ $(,)?) => ($(
         impl $t {
-            /// Add a header.
+            /// Set a header, replacing any previous values of it.
             ///
             /// ```
             /// # use waki::ResponseBuilder;
@@ -142,7 +508,74 @@ macro_rules! impl_common_set_methods {
                 self
             }
 
-            /// Add a set of headers.
+            /// Append a header, keeping the previous values of it.
+            ///
+            /// ```
+            /// # use waki::ResponseBuilder;
+            /// # fn run() {
+            /// # let r = ResponseBuilder::new();
+            /// r.append_header("Set-Cookie", "a=1")
+            ///     .append_header("Set-Cookie", "b=2");
+            /// # }
+            /// ```
+            pub fn append_header<K, V>(mut self, key: K, value: V) -> Self
+            where
+                K: IntoHeaderName,
+                V: TryInto<HeaderValue>,
+                <V as TryInto<HeaderValue>>::Error: Into<Error>,
+            {
+                let mut err = None;
+                if let Ok(ref mut inner) = self.inner {
+                    match value.try_into().map_err(|e| e.into()) {
+                        Ok(v) => {
+                            inner.headers.append(key, v);
+                        }
+                        Err(e) => err = Some(e),
+                    };
+                }
+                if let Some(e) = err {
+                    self.inner = Err(e);
+                }
+                self
+            }
+
+            /// Remove all the values of a header.
+            ///
+            /// ```
+            /// # use waki::ResponseBuilder;
+            /// # fn run() {
+            /// # let r = ResponseBuilder::new();
+            /// r.remove_header("Set-Cookie");
+            /// # }
+            /// ```
+            #[inline]
+            pub fn remove_header<K: AsHeaderName>(mut self, key: K) -> Self {
+                if let Ok(ref mut inner) = self.inner {
+                    inner.headers.remove(key);
+                }
+                self
+            }
+
+            /// Get a mutable reference to the headers set so far, `None` if an error occurred
+            /// while building.
+            ///
+            /// ```
+            /// # use waki::ResponseBuilder;
+            /// # fn run() {
+            /// let mut r = ResponseBuilder::new();
+            /// if let Some(headers) = r.headers_mut() {
+            ///     headers.append("Link", "</style.css>; rel=preload".parse().unwrap());
+            /// }
+            /// # }
+            /// ```
+            #[inline]
+            pub fn headers_mut(&mut self) -> Option<&mut HeaderMap> {
+                self.inner.as_mut().ok().map(|inner| &mut inner.headers)
+            }
+
+            /// Set several headers, each replacing any previous values of it.
+            ///
+            /// To set several values of the same header, use `append_header` instead.
             ///
             /// ```
             /// # use waki::ResponseBuilder;
@@ -178,6 +611,87 @@ macro_rules! impl_common_set_methods {
                 self
             }
 
+            /// Set the `Content-Type` header.
+            ///
+            /// ```
+            /// # use waki::ResponseBuilder;
+            /// # fn run() {
+            /// # let r = ResponseBuilder::new();
+            /// r.content_type(mime::TEXT_HTML_UTF_8);
+            /// # }
+            /// ```
+            #[inline]
+            pub fn content_type(self, mime: Mime) -> Self {
+                self.header(CONTENT_TYPE, mime.as_ref())
+            }
+
+            /// Set the `Content-Length` header.
+            #[inline]
+            pub fn content_length(self, len: u64) -> Self {
+                self.header(CONTENT_LENGTH, len)
+            }
+
+            /// Set the `Accept` header.
+            ///
+            /// ```
+            /// # use waki::{QualityItem, ResponseBuilder};
+            /// # fn run() {
+            /// # let r = ResponseBuilder::new();
+            /// r.accept([
+            ///     QualityItem::new(mime::APPLICATION_JSON, 1.0),
+            ///     QualityItem::new(mime::STAR_STAR, 0.1),
+            /// ]);
+            /// # }
+            /// ```
+            pub fn accept<I: IntoIterator<Item = QualityItem<Mime>>>(self, ranges: I) -> Self {
+                let ranges = ranges.into_iter().map(|v| v.to_string()).collect::<Vec<_>>();
+                self.header(ACCEPT, ranges.join(", "))
+            }
+
+            /// Set the `Authorization` header.
+            ///
+            /// ```
+            /// # use waki::{Authorization, ResponseBuilder};
+            /// # fn run() {
+            /// # let r = ResponseBuilder::new();
+            /// r.authorization(Authorization::basic("user", "password"));
+            /// # }
+            /// ```
+            #[inline]
+            pub fn authorization(self, credentials: Authorization) -> Self {
+                self.header(AUTHORIZATION, credentials.to_string())
+            }
+
+            /// Set the `If-None-Match` header.
+            #[inline]
+            pub fn if_none_match(self, value: IfNoneMatch) -> Self {
+                self.header(IF_NONE_MATCH, value.to_string())
+            }
+
+            /// Set the `If-Match` header.
+            #[inline]
+            pub fn if_match(self, value: IfMatch) -> Self {
+                self.header(IF_MATCH, value.to_string())
+            }
+
+            /// Set the `If-Modified-Since` header.
+            #[inline]
+            pub fn if_modified_since(self, date: SystemTime) -> Self {
+                self.header(IF_MODIFIED_SINCE, httpdate::fmt_http_date(date))
+            }
+
+            /// Set the `If-Unmodified-Since` header.
+            #[inline]
+            pub fn if_unmodified_since(self, date: SystemTime) -> Self {
+                self.header(IF_UNMODIFIED_SINCE, httpdate::fmt_http_date(date))
+            }
+
+            /// Set the `Date` header.
+            #[inline]
+            pub fn date(self, date: SystemTime) -> Self {
+                self.header(DATE, httpdate::fmt_http_date(date))
+            }
+
             /// Set the body.
             ///
             /// ```
@@ -195,6 +709,64 @@ macro_rules! impl_common_set_methods {
                 self
             }
 
+            /// Set the body from the body of an incoming request or response.
+            ///
+            /// If nothing has been read from the reader yet, the data is spliced between the
+            /// WASI streams by the host instead of being copied through the component.
+            ///
+            /// ```
+            /// use waki::{handler, ErrorCode, Request, Response};
+            ///
+            /// #[handler]
+            /// fn echo(req: Request) -> Result<Response, ErrorCode> {
+            ///     Response::builder().body_reader(req.into_reader()).build()
+            /// }
+            /// ```
+            #[inline]
+            pub fn body_reader(mut self, reader: BodyReader) -> Self {
+                if let Ok(ref mut inner) = self.inner {
+                    inner.body = reader.into_body();
+                }
+                self
+            }
+
+            /// Set a body written by a future as it runs, so that it can be streamed while
+            /// awaiting other I/O.
+            ///
+            /// The future is started when the body is sent, and the body ends when it completes.
+            ///
+            /// # Optional
+            ///
+            /// This requires the `async` feature enabled.
+            ///
+            /// ```
+            /// use waki::{handler, Client, ErrorCode, Request, Response};
+            ///
+            /// #[handler]
+            /// async fn hello(_req: Request) -> Result<Response, ErrorCode> {
+            ///     Response::builder()
+            ///         .body_writer(|mut writer| async move {
+            ///             writer.write_all(b"origin: ").await?;
+            ///             writer.flush().await?;
+            ///             let resp = Client::new().get("https://httpbin.org/ip").send_async().await?;
+            ///             writer.write_all(&resp.body()?).await
+            ///         })
+            ///         .build()
+            /// }
+            /// ```
+            #[cfg(feature = "async")]
+            #[inline]
+            pub fn body_writer<F, Fut>(mut self, write: F) -> Self
+            where
+                F: FnOnce(BodyWriter) -> Fut + Send + 'static,
+                Fut: std::future::Future<Output = Result<()>> + 'static,
+            {
+                if let Ok(ref mut inner) = self.inner {
+                    inner.body = Body::Writer(Box::new(move |writer| Box::pin(write(writer))));
+                }
+                self
+            }
+
             /// Set a JSON body.
             ///
             /// # Optional
@@ -225,6 +797,110 @@ macro_rules! impl_common_set_methods {
                 self
             }
 
+            /// Set a newline delimited JSON body, serializing the values of `iter` one at a
+            /// time as the body is sent, without collecting them.
+            ///
+            /// # Optional
+            ///
+            /// This requires the `json` feature enabled.
+            ///
+            /// ```
+            /// # use std::collections::HashMap;
+            /// # use waki::ResponseBuilder;
+            /// # fn run() {
+            /// # let r = ResponseBuilder::new();
+            /// r.json_lines((0..3).map(|id| HashMap::from([("id", id)])));
+            /// # }
+            /// ```
+            #[cfg(feature = "json")]
+            pub fn json_lines<I>(mut self, iter: I) -> Self
+            where
+                I: IntoIterator,
+                I::IntoIter: Send + 'static,
+                I::Item: Serialize,
+            {
+                if let Ok(ref mut inner) = self.inner {
+                    inner.headers.insert(CONTENT_TYPE, HeaderValue::from_static(NDJSON));
+                    inner.body = Body::Reader(Box::new(JsonLinesReader::new(iter.into_iter())));
+                }
+                self
+            }
+
+            /// Set a CBOR body.
+            ///
+            /// # Optional
+            ///
+            /// This requires the `cbor` feature enabled.
+            ///
+            /// ```
+            /// # use std::collections::HashMap;
+            /// # use waki::ResponseBuilder;
+            /// # fn run() {
+            /// # let r = ResponseBuilder::new();
+            /// r.cbor(&HashMap::from([("data", "hello")]));
+            /// # }
+            /// ```
+            #[cfg(feature = "cbor")]
+            pub fn cbor<T: Serialize + ?Sized>(mut self, value: &T) -> Self {
+                let mut err = None;
+                if let Ok(ref mut inner) = self.inner {
+                    inner.headers.insert(CONTENT_TYPE, "application/cbor".parse().unwrap());
+                    let mut data = vec![];
+                    match ciborium::into_writer(value, &mut data) {
+                        Ok(()) => inner.body = Body::Bytes(data),
+                        Err(e) => err = Some(e.into()),
+                    }
+                }
+                if let Some(e) = err {
+                    self.inner = Err(e);
+                }
+                self
+            }
+
+            /// Set a MessagePack body, with structs serialized as maps.
+            ///
+            /// # Optional
+            ///
+            /// This requires the `msgpack` feature enabled.
+            ///
+            /// ```
+            /// # use std::collections::HashMap;
+            /// # use waki::ResponseBuilder;
+            /// # fn run() {
+            /// # let r = ResponseBuilder::new();
+            /// r.msgpack(&HashMap::from([("data", "hello")]));
+            /// # }
+            /// ```
+            #[cfg(feature = "msgpack")]
+            pub fn msgpack<T: Serialize + ?Sized>(mut self, value: &T) -> Self {
+                let mut err = None;
+                if let Ok(ref mut inner) = self.inner {
+                    inner.headers.insert(CONTENT_TYPE, "application/msgpack".parse().unwrap());
+                    match rmp_serde::to_vec_named(value) {
+                        Ok(data) => inner.body = Body::Bytes(data),
+                        Err(e) => err = Some(e.into()),
+                    }
+                }
+                if let Some(e) = err {
+                    self.inner = Err(e);
+                }
+                self
+            }
+
+            /// Set a Protocol Buffers body.
+            ///
+            /// # Optional
+            ///
+            /// This requires the `protobuf` feature enabled.
+            #[cfg(feature = "protobuf")]
+            pub fn protobuf<T: prost::Message>(mut self, message: &T) -> Self {
+                if let Ok(ref mut inner) = self.inner {
+                    inner.headers.insert(CONTENT_TYPE, "application/x-protobuf".parse().unwrap());
+                    inner.body = Body::Bytes(message.encode_to_vec());
+                }
+                self
+            }
+
             /// Set a form body.
             ///
             /// ```
@@ -293,6 +969,43 @@ macro_rules! impl_common_set_methods {
                 }
                 self
             }
+
+            /// Set a multipart body of any subtype.
+            ///
+            /// # Optional
+            ///
+            /// This requires the `multipart` feature enabled.
+            ///
+            /// ```
+            /// # use waki::ResponseBuilder;
+            /// # fn run() {
+            /// # let r = ResponseBuilder::new();
+            /// use waki::multipart::{BodyPart, Multipart};
+            ///
+            /// r.multipart_body(
+            ///     Multipart::mixed()
+            ///         .part(BodyPart::new("first").mime(mime::TEXT_PLAIN))
+            ///         .part(BodyPart::new("second").mime(mime::TEXT_PLAIN)),
+            /// );
+            /// # }
+            /// ```
+            #[cfg(feature = "multipart")]
+            pub fn multipart_body(mut self, multipart: Multipart) -> Self {
+                let mut err = None;
+                if let Ok(ref mut inner) = self.inner {
+                    match multipart.check() {
+                        Ok(v) => {
+                            inner.headers.insert(CONTENT_TYPE, v);
+                            inner.body = Body::Bytes(multipart.build());
+                        }
+                        Err(e) => err = Some(e),
+                    }
+                }
+                if let Some(e) = err {
+                    self.inner = Err(e);
+                }
+                self
+            }
         }
     )+)
 }
@@ -0,0 +1,102 @@
+use anyhow::{anyhow, Result};
+#[cfg(feature = "encoding")]
+use encoding_rs::{Encoding, UTF_8};
+use mime::Mime;
+
+/// Decode a text body with the `charset` parameter of its `Content-Type`, UTF-8 by default.
+///
+/// When `lossy` is set, malformed sequences are replaced with `U+FFFD` and unknown charsets fall
+/// back to UTF-8 instead of failing.
+pub(crate) fn decode(bytes: &[u8], content_type: Option<&Mime>, lossy: bool) -> Result<String> {
+    let charset = content_type.and_then(|m| m.get_param(mime::CHARSET));
+    decode_charset(bytes, charset.as_ref().map(|c| c.as_str()), lossy)
+}
+
+#[cfg(feature = "encoding")]
+fn decode_charset(bytes: &[u8], charset: Option<&str>, lossy: bool) -> Result<String> {
+    let encoding = match charset.map(|label| (label, Encoding::for_label(label.as_bytes()))) {
+        None => UTF_8,
+        Some((_, Some(encoding))) => encoding,
+        Some(_) if lossy => UTF_8,
+        Some((label, None)) => return Err(anyhow!("unsupported charset: {label}")),
+    };
+    // a byte order mark takes precedence over the charset
+    let (text, encoding, malformed) = encoding.decode(bytes);
+    if malformed && !lossy {
+        return Err(anyhow!("invalid {} text", encoding.name()));
+    }
+    Ok(text.into_owned())
+}
+
+#[cfg(not(feature = "encoding"))]
+fn decode_charset(bytes: &[u8], charset: Option<&str>, lossy: bool) -> Result<String> {
+    match charset {
+        Some(label)
+            if !lossy
+                && !["utf-8", "utf8", "us-ascii"]
+                    .iter()
+                    .any(|v| label.eq_ignore_ascii_case(v)) =>
+        {
+            Err(anyhow!(
+                "unsupported charset: {label}, enable the `encoding` feature to decode it"
+            ))
+        }
+        _ => {
+            let bytes = bytes.strip_prefix(b"\xEF\xBB\xBF").unwrap_or(bytes);
+            if lossy {
+                Ok(String::from_utf8_lossy(bytes).into_owned())
+            } else {
+                Ok(std::str::from_utf8(bytes)?.to_string())
+            }
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_decode() -> Result<()> {
+        let utf8 = "text/plain; charset=UTF-8".parse::<Mime>()?;
+        assert_eq!(decode("héllo".as_bytes(), Some(&utf8), false)?, "héllo");
+        assert_eq!(decode(b"\xEF\xBB\xBFhello", None, false)?, "hello");
+        assert!(decode(b"h\xE9llo", None, false).is_err());
+        assert_eq!(decode(b"h\xE9llo", None, true)?, "h\u{FFFD}llo");
+
+        Ok(())
+    }
+
+    #[cfg(not(feature = "encoding"))]
+    #[test]
+    fn test_decode_unsupported() -> Result<()> {
+        let latin1 = "text/html; charset=ISO-8859-1".parse::<Mime>()?;
+        assert!(decode(b"h\xE9llo", Some(&latin1), false).is_err());
+        assert_eq!(decode(b"h\xE9llo", Some(&latin1), true)?, "h\u{FFFD}llo");
+        Ok(())
+    }
+
+    #[cfg(feature = "encoding")]
+    #[test]
+    fn test_decode_encoding() -> Result<()> {
+        let latin1 = "text/html; charset=ISO-8859-1".parse::<Mime>()?;
+        assert_eq!(decode(b"h\xE9llo", Some(&latin1), false)?, "héllo");
+
+        let shift_jis = "text/plain; charset=Shift_JIS".parse::<Mime>()?;
+        assert_eq!(
+            decode(
+                b"\x82\xB1\x82\xF1\x82\xC9\x82\xBF\x82\xCD",
+                Some(&shift_jis),
+                false
+            )?,
+            "こんにちは"
+        );
+        // the byte order mark wins over the charset
+        assert_eq!(decode(b"\xFF\xFEh\x00i\x00", Some(&latin1), false)?, "hi");
+
+        let unknown = "text/plain; charset=unknown".parse::<Mime>()?;
+        assert!(decode(b"hello", Some(&unknown), false).is_err());
+        assert_eq!(decode(b"hello", Some(&unknown), true)?, "hello");
+        Ok(())
+    }
+}
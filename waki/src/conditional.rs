@@ -0,0 +1,254 @@
+use crate::{
+    body::Body,
+    header::{
+        HeaderValue, CONTENT_ENCODING, CONTENT_LANGUAGE, CONTENT_LENGTH, CONTENT_RANGE,
+        CONTENT_TYPE, ETAG, LAST_MODIFIED, TRANSFER_ENCODING,
+    },
+    EntityTag, IfMatch, IfNoneMatch, Method, Request, Response,
+};
+
+use std::time::SystemTime;
+
+/// The conditional headers of a request, see RFC 9110 section 13.
+///
+/// [`handle_response`](crate::handle_response) only evaluates `If-None-Match` and
+/// `If-Modified-Since` of `GET` and `HEAD` requests against the response of the handler: the
+/// preconditions of the other requests must be checked before any change is made, with
+/// [`Request::check_preconditions`].
+#[doc(hidden)]
+#[derive(Default)]
+pub struct Preconditions {
+    safe: bool,
+    if_match: Option<IfMatch>,
+    if_none_match: Option<IfNoneMatch>,
+    if_modified_since: Option<SystemTime>,
+    if_unmodified_since: Option<SystemTime>,
+}
+
+impl Preconditions {
+    /// Capture the preconditions evaluated once the handler returns.
+    pub fn new(req: &Request) -> Self {
+        if !matches!(req.method, Method::Get | Method::Head) {
+            return Self::default();
+        }
+        Self {
+            safe: true,
+            if_none_match: req.if_none_match(),
+            if_modified_since: req.if_modified_since(),
+            ..Default::default()
+        }
+    }
+
+    /// Capture all the preconditions of the request.
+    pub(crate) fn all(req: &Request) -> Self {
+        Self {
+            safe: matches!(req.method, Method::Get | Method::Head),
+            if_match: req.if_match(),
+            if_none_match: req.if_none_match(),
+            if_modified_since: req.if_modified_since(),
+            if_unmodified_since: req.if_unmodified_since(),
+        }
+    }
+
+    /// Evaluate the preconditions in the order of RFC 9110 section 13.2.2 against the current
+    /// validators, returning the status code answering the request instead, if any.
+    pub(crate) fn evaluate(
+        &self,
+        etag: Option<&EntityTag>,
+        last_modified: Option<SystemTime>,
+    ) -> Option<u16> {
+        if let Some(if_match) = &self.if_match {
+            let matches =
+                matches!(if_match, IfMatch::Any) || etag.is_some_and(|etag| if_match.matches(etag));
+            if !matches {
+                return Some(412);
+            }
+        } else if let (Some(since), Some(last_modified)) = (self.if_unmodified_since, last_modified)
+        {
+            if last_modified > since {
+                return Some(412);
+            }
+        }
+
+        if let Some(if_none_match) = &self.if_none_match {
+            let matches = matches!(if_none_match, IfNoneMatch::Any)
+                || etag.is_some_and(|etag| if_none_match.matches(etag));
+            if matches {
+                return Some(if self.safe { 304 } else { 412 });
+            }
+        } else if let (true, Some(since), Some(last_modified)) =
+            (self.safe, self.if_modified_since, last_modified)
+        {
+            if last_modified <= since {
+                return Some(304);
+            }
+        }
+        None
+    }
+
+    /// Turn the response into `304 Not Modified` with an empty body when the representation is
+    /// unchanged.
+    pub fn apply(&self, mut resp: Response) -> Response {
+        // the preconditions only apply to a successful response
+        if !(200..300).contains(&resp.status_code) {
+            return resp;
+        }
+        if let Some(status_code) = self.evaluate(resp.etag().as_ref(), resp.last_modified()) {
+            resp.status_code = status_code;
+            resp.body = Body::Bytes(vec![]);
+            for name in [
+                CONTENT_ENCODING,
+                CONTENT_LANGUAGE,
+                CONTENT_LENGTH,
+                CONTENT_RANGE,
+                CONTENT_TYPE,
+                TRANSFER_ENCODING,
+            ] {
+                resp.headers.remove(name);
+            }
+        }
+        resp
+    }
+}
+
+/// Build the empty response answering a request whose precondition failed, with the validators
+/// of a `304 Not Modified` response.
+pub(crate) fn precondition_response(
+    status_code: u16,
+    etag: Option<&EntityTag>,
+    last_modified: Option<SystemTime>,
+) -> Response {
+    let mut resp = Response::new();
+    resp.status_code = status_code;
+    if status_code == 304 {
+        if let Some(etag) = etag.and_then(|etag| HeaderValue::try_from(etag.to_string()).ok()) {
+            resp.headers.insert(ETAG, etag);
+        }
+        if let Some(last_modified) = last_modified {
+            let value = httpdate::fmt_http_date(last_modified);
+            resp.headers
+                .insert(LAST_MODIFIED, HeaderValue::try_from(value).unwrap());
+        }
+    }
+    resp
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::{
+        header::{
+            HeaderName, ETAG, IF_MATCH, IF_MODIFIED_SINCE, IF_NONE_MATCH, IF_UNMODIFIED_SINCE,
+        },
+        EntityTag,
+    };
+    use std::time::{Duration, UNIX_EPOCH};
+
+    const BEFORE: &str = "Tue, 14 Nov 2023 22:13:19 GMT";
+    const AT: &str = "Tue, 14 Nov 2023 22:13:20 GMT";
+
+    fn apply(method: Method, headers: &[(HeaderName, &str)], status_code: u16) -> Response {
+        let mut req = Request::new(method, Default::default());
+        for (name, value) in headers {
+            req.headers.insert(name, value.parse().unwrap());
+        }
+        let resp = Response::builder()
+            .status_code(status_code)
+            .etag(EntityTag::strong("v2"))
+            .last_modified(UNIX_EPOCH + Duration::from_secs(1_700_000_000))
+            .body("hello")
+            .content_length(5)
+            .build()
+            .unwrap();
+        Preconditions::new(&req).apply(resp)
+    }
+
+    fn status(method: Method, headers: &[(HeaderName, &str)]) -> u16 {
+        apply(method, headers, 200).status_code
+    }
+
+    #[test]
+    fn test_if_none_match() {
+        assert_eq!(status(Method::Get, &[]), 200);
+        assert_eq!(
+            status(Method::Get, &[(IF_NONE_MATCH, "\"v1\", W/\"v2\"")]),
+            304
+        );
+        assert_eq!(status(Method::Get, &[(IF_NONE_MATCH, "\"v1\"")]), 200);
+        assert_eq!(status(Method::Get, &[(IF_MODIFIED_SINCE, AT)]), 304);
+        assert_eq!(status(Method::Get, &[(IF_MODIFIED_SINCE, BEFORE)]), 200);
+        assert_eq!(
+            status(
+                Method::Get,
+                &[(IF_NONE_MATCH, "\"v1\""), (IF_MODIFIED_SINCE, AT)]
+            ),
+            200
+        );
+
+        let resp = apply(Method::Get, &[(IF_NONE_MATCH, "\"v2\"")], 200);
+        assert_eq!(resp.headers[ETAG], "\"v2\"");
+        assert!(!resp.headers.contains_key(CONTENT_LENGTH));
+        assert!(matches!(resp.body, Body::Bytes(ref b) if b.is_empty()));
+
+        // only successful responses are affected
+        let resp = apply(Method::Get, &[(IF_NONE_MATCH, "*")], 404);
+        assert_eq!(resp.status_code, 404);
+    }
+
+    #[test]
+    fn test_unsafe_methods() {
+        // only checked by the handler, before any change is made
+        assert_eq!(status(Method::Put, &[(IF_MATCH, "\"v1\"")]), 200);
+        assert_eq!(status(Method::Put, &[(IF_NONE_MATCH, "*")]), 200);
+        assert_eq!(status(Method::Post, &[(IF_MODIFIED_SINCE, AT)]), 200);
+        assert_eq!(status(Method::Get, &[(IF_MATCH, "\"v1\"")]), 200);
+    }
+
+    fn check(method: Method, headers: &[(HeaderName, &str)]) -> Option<u16> {
+        let mut req = Request::new(method, Default::default());
+        for (name, value) in headers {
+            req.headers.insert(name, value.parse().unwrap());
+        }
+        let etag = EntityTag::strong("v2");
+        let last_modified = UNIX_EPOCH + Duration::from_secs(1_700_000_000);
+        req.check_preconditions(Some(&etag), Some(last_modified))
+            .map(|resp| resp.status_code)
+    }
+
+    #[test]
+    fn test_check_preconditions() {
+        assert_eq!(check(Method::Put, &[]), None);
+        assert_eq!(check(Method::Put, &[(IF_MATCH, "\"v2\"")]), None);
+        assert_eq!(check(Method::Put, &[(IF_MATCH, "W/\"v2\"")]), Some(412));
+        assert_eq!(check(Method::Put, &[(IF_MATCH, "*")]), None);
+        assert_eq!(
+            check(Method::Put, &[(IF_UNMODIFIED_SINCE, BEFORE)]),
+            Some(412)
+        );
+        assert_eq!(check(Method::Put, &[(IF_UNMODIFIED_SINCE, AT)]), None);
+        // If-Match takes precedence over If-Unmodified-Since
+        assert_eq!(
+            check(
+                Method::Put,
+                &[(IF_MATCH, "\"v2\""), (IF_UNMODIFIED_SINCE, BEFORE)]
+            ),
+            None
+        );
+        assert_eq!(check(Method::Put, &[(IF_NONE_MATCH, "*")]), Some(412));
+        assert_eq!(
+            check(
+                Method::Get,
+                &[(IF_MATCH, "\"v1\""), (IF_NONE_MATCH, "\"v2\"")]
+            ),
+            Some(412)
+        );
+        assert_eq!(check(Method::Get, &[(IF_NONE_MATCH, "\"v2\"")]), Some(304));
+
+        let mut req = Request::new(Method::Get, Default::default());
+        req.headers.insert(IF_NONE_MATCH, "\"v2\"".parse().unwrap());
+        let resp = req
+            .check_preconditions(Some(&EntityTag::strong("v2")), None)
+            .unwrap();
+        assert_eq!(resp.headers[ETAG], "\"v2\"");
+    }
+}
@@ -0,0 +1,282 @@
+use crate::{
+    header::{CONTENT_RANGE, ETAG, IF_RANGE, LAST_MODIFIED},
+    Client, ContentRange, EntityTag, Response,
+};
+
+use anyhow::{anyhow, Result};
+use std::io::Write;
+
+const CHUNK_SIZE: usize = 64 * 1024;
+
+/// A hash computed over the downloaded bytes, see [`Download::checksum`].
+///
+/// It is implemented for the hashes of the `sha2` and `md-5` crates when one of the `digest-auth`,
+/// `jwt` or `signing` features is enabled.
+pub trait Checksum {
+    /// Feed the next downloaded bytes.
+    fn update(&mut self, data: &[u8]);
+
+    /// Compute the digest of all the bytes fed.
+    fn finalize(self: Box<Self>) -> Vec<u8>;
+}
+
+#[cfg(any(feature = "digest-auth", feature = "jwt", feature = "signing"))]
+impl<D: sha2::Digest> Checksum for D {
+    fn update(&mut self, data: &[u8]) {
+        sha2::Digest::update(self, data);
+    }
+
+    fn finalize(self: Box<Self>) -> Vec<u8> {
+        sha2::Digest::finalize(*self).to_vec()
+    }
+}
+
+/// A download to a writer resuming after transient failures, built with
+/// [`Client::download_to`].
+///
+/// When sending the request or reading the body fails, or the body ends before its
+/// `Content-Length`, the download is resumed with a `Range` request starting at the first
+/// missing byte. The `ETag`, or else the `Last-Modified` date, of the first response is sent as
+/// `If-Range`, so a resource modified in the meantime makes the download fail instead of mixing
+/// two versions. The `Content-Range` of each resumed response is checked against the bytes
+/// already received. A download whose first response has neither a strong `ETag` nor a
+/// `Last-Modified` date fails instead of being resumed, as a modification could not be detected.
+pub struct Download<'a, W> {
+    client: &'a Client,
+    url: String,
+    writer: W,
+    max_retries: u32,
+    expected_length: Option<u64>,
+    checksum: Option<(Box<dyn Checksum + 'a>, Vec<u8>)>,
+}
+
+impl<'a, W: Write> Download<'a, W> {
+    pub(crate) fn new(client: &'a Client, url: &str, writer: W) -> Self {
+        Self {
+            client,
+            url: url.to_string(),
+            writer,
+            max_retries: 3,
+            expected_length: None,
+            checksum: None,
+        }
+    }
+
+    /// Set how many times the download is resumed before giving up.
+    ///
+    /// Default value: 3.
+    #[inline]
+    pub fn max_retries(mut self, max_retries: u32) -> Self {
+        self.max_retries = max_retries;
+        self
+    }
+
+    /// Fail unless exactly `len` bytes are downloaded.
+    #[inline]
+    pub fn expected_length(mut self, len: u64) -> Self {
+        self.expected_length = Some(len);
+        self
+    }
+
+    /// Fail unless the digest of the downloaded bytes computed by `checksum` is `expected`.
+    #[inline]
+    pub fn checksum<C: Checksum + 'a, E: Into<Vec<u8>>>(
+        mut self,
+        checksum: C,
+        expected: E,
+    ) -> Self {
+        self.checksum = Some((Box::new(checksum), expected.into()));
+        self
+    }
+
+    /// Run the download, returning the number of bytes written.
+    pub fn send(mut self) -> Result<u64> {
+        let mut received = 0;
+        let mut validator = None;
+        let mut total = None;
+        let mut retries = 0;
+        let mut buf = vec![0; CHUNK_SIZE];
+        loop {
+            match self.transfer(&mut received, &mut validator, &mut total, &mut buf) {
+                Ok(()) => break,
+                Err(Failure::Fatal(e)) => return Err(e),
+                Err(Failure::Transient(e)) if received > 0 && validator.is_none() => {
+                    return Err(e.context(
+                        "the download cannot be resumed without a strong ETag or a Last-Modified date",
+                    ))
+                }
+                Err(Failure::Transient(e)) if retries >= self.max_retries => {
+                    return Err(e.context(format!("download failed after {retries} retries")))
+                }
+                Err(Failure::Transient(_)) => retries += 1,
+            }
+        }
+        self.writer.flush()?;
+
+        if let Some(expected) = self.expected_length {
+            if received != expected {
+                return Err(anyhow!(
+                    "downloaded {received} bytes, expected {expected} bytes"
+                ));
+            }
+        }
+        if let Some((checksum, expected)) = self.checksum {
+            if checksum.finalize() != expected {
+                return Err(anyhow!("checksum mismatch"));
+            }
+        }
+        Ok(received)
+    }
+
+    /// Request the bytes from `received`, writing them until the body ends.
+    fn transfer(
+        &mut self,
+        received: &mut u64,
+        validator: &mut Option<String>,
+        total: &mut Option<u64>,
+        buf: &mut [u8],
+    ) -> Result<(), Failure> {
+        let mut builder = self.client.get(&self.url);
+        if *received > 0 {
+            builder = builder.range(*received..);
+            if let Some(validator) = validator {
+                builder = builder.header(IF_RANGE, validator.as_str());
+            }
+        }
+        let resp = builder.send().map_err(Failure::Transient)?;
+
+        if *received == 0 {
+            if resp.status_code() != 200 {
+                return Err(Failure::Fatal(anyhow!(
+                    "unexpected status code: {}",
+                    resp.status_code()
+                )));
+            }
+            *validator = validator_of(&resp);
+            *total = resp.content_length();
+        } else {
+            let range = check_resumed(&resp, *received, *total).map_err(Failure::Fatal)?;
+            *total = total.or(range.complete_length);
+        }
+
+        loop {
+            let n = resp.read_chunk(buf).map_err(Failure::Transient)?;
+            if n == 0 {
+                break;
+            }
+            self.writer
+                .write_all(&buf[..n])
+                .map_err(|e| Failure::Fatal(e.into()))?;
+            if let Some((checksum, _)) = &mut self.checksum {
+                checksum.update(&buf[..n]);
+            }
+            *received += n as u64;
+        }
+        match *total {
+            Some(total) if *received < total => Err(Failure::Transient(anyhow!(
+                "body ended after {received} of {total} bytes"
+            ))),
+            _ => Ok(()),
+        }
+    }
+}
+
+enum Failure {
+    /// The download can be resumed.
+    Transient(anyhow::Error),
+    Fatal(anyhow::Error),
+}
+
+/// The `If-Range` validator of a response: a strong `ETag`, or else its `Last-Modified` date.
+fn validator_of(resp: &Response) -> Option<String> {
+    let etag = resp
+        .header(ETAG)
+        .and_then(|v| v.to_str().ok())
+        .filter(|v| v.parse::<EntityTag>().is_ok_and(|etag| !etag.is_weak()));
+    etag.or_else(|| resp.header(LAST_MODIFIED).and_then(|v| v.to_str().ok()))
+        .map(str::to_string)
+}
+
+/// Check that a resumed response continues the download at `received`.
+fn check_resumed(resp: &Response, received: u64, total: Option<u64>) -> Result<ContentRange> {
+    match resp.status_code() {
+        206 => {}
+        200 => return Err(anyhow!("the resource changed, or ranges are not supported")),
+        status_code => return Err(anyhow!("unexpected status code: {status_code}")),
+    }
+    let range = resp
+        .header(CONTENT_RANGE)
+        .ok_or_else(|| anyhow!("missing Content-Range"))?
+        .to_str()?
+        .parse::<ContentRange>()?;
+    if range.start != received {
+        return Err(anyhow!(
+            "Content-Range {range} does not start at byte {received}"
+        ));
+    }
+    if let (Some(total), Some(complete_length)) = (total, range.complete_length) {
+        if total != complete_length {
+            return Err(anyhow!(
+                "Content-Range {range} does not match the length {total}"
+            ));
+        }
+    }
+    Ok(range)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn resumed(status_code: u16, content_range: Option<&str>) -> Response {
+        let mut builder = Response::builder().status_code(status_code);
+        if let Some(content_range) = content_range {
+            builder = builder.header(CONTENT_RANGE, content_range);
+        }
+        builder.build().unwrap()
+    }
+
+    #[test]
+    fn test_check_resumed() {
+        let resp = resumed(206, Some("bytes 10-19/20"));
+        assert_eq!(
+            check_resumed(&resp, 10, Some(20)).unwrap(),
+            ContentRange::new(10, 19, Some(20))
+        );
+        assert!(check_resumed(&resp, 10, None).is_ok());
+        let resp = resumed(206, Some("bytes 10-19/*"));
+        assert!(check_resumed(&resp, 10, Some(20)).is_ok());
+
+        // the resource changed or the server ignored the range
+        assert!(check_resumed(&resumed(200, None), 10, Some(20)).is_err());
+        assert!(check_resumed(&resumed(416, Some("bytes */20")), 10, Some(20)).is_err());
+        assert!(check_resumed(&resumed(206, None), 10, Some(20)).is_err());
+        assert!(check_resumed(&resumed(206, Some("bytes 0-19/20")), 10, Some(20)).is_err());
+        assert!(check_resumed(&resumed(206, Some("bytes 10-29/30")), 10, Some(20)).is_err());
+        assert!(check_resumed(&resumed(206, Some("invalid")), 10, Some(20)).is_err());
+    }
+
+    #[test]
+    fn test_validator_of() {
+        let resp = Response::builder()
+            .header(ETAG, "\"v1\"")
+            .header(LAST_MODIFIED, "Tue, 14 Nov 2023 22:13:20 GMT")
+            .build()
+            .unwrap();
+        assert_eq!(validator_of(&resp).as_deref(), Some("\"v1\""));
+        let resp = Response::builder()
+            .header(ETAG, "W/\"v1\"")
+            .header(LAST_MODIFIED, "Tue, 14 Nov 2023 22:13:20 GMT")
+            .build()
+            .unwrap();
+        assert_eq!(
+            validator_of(&resp).as_deref(),
+            Some("Tue, 14 Nov 2023 22:13:20 GMT")
+        );
+        let resp = Response::builder()
+            .header(ETAG, "W/\"v1\"")
+            .build()
+            .unwrap();
+        assert_eq!(validator_of(&resp), None);
+    }
+}
@@ -0,0 +1,242 @@
+//! Static file serving.
+//!
+//! Files are read from the directories preopened by the host through `wasi:filesystem`, with
+//! paths relative to a preopen, for instance `.` when the host maps a directory to it.
+//!
+//! ```
+//! use waki::{fs::ServeDir, handler, ErrorCode, Request, Response};
+//!
+//! #[handler]
+//! fn hello(req: Request) -> Result<Response, ErrorCode> {
+//!     ServeDir::new("public").prefix("/static").serve(&req)
+//! }
+//! ```
+
+use crate::{
+    header::{ALLOW, LOCATION},
+    EntityTag, ErrorCode, Method, Ranged, Request, Response,
+};
+
+use mime::Mime;
+use percent_encoding::percent_decode_str;
+use std::fs::{self, File, Metadata};
+use std::io;
+use std::path::{Path, PathBuf};
+use std::time::{SystemTime, UNIX_EPOCH};
+
+/// Serve the files of a directory, mapping the request path to a file under it.
+///
+/// Only `GET` and `HEAD` requests are allowed. Paths containing `..` segments are rejected, a
+/// directory is served through its index file, `index.html` by default, and the `Content-Type`
+/// is guessed from the file extension. Responses carry a weak `ETag` and a `Last-Modified` header
+/// computed from the file metadata, and byte ranges are answered through [`Ranged`].
+#[derive(Debug, Clone)]
+pub struct ServeDir {
+    root: PathBuf,
+    prefix: String,
+    index_file: Option<String>,
+}
+
+impl ServeDir {
+    /// Serve the directory at `root`, relative to a preopened directory.
+    pub fn new<P: Into<PathBuf>>(root: P) -> Self {
+        Self {
+            root: root.into(),
+            prefix: String::new(),
+            index_file: Some("index.html".to_string()),
+        }
+    }
+
+    /// Only serve the requests under `prefix`, such as `/static`, stripping it from the path.
+    pub fn prefix<P: Into<String>>(mut self, prefix: P) -> Self {
+        self.prefix = prefix.into().trim_end_matches('/').to_string();
+        self
+    }
+
+    /// Set the file served for directories, `None` to answer them with `404 Not Found`.
+    pub fn index_file<F: Into<String>>(mut self, index_file: Option<F>) -> Self {
+        self.index_file = index_file.map(Into::into);
+        self
+    }
+
+    /// Map a request path to a file, `None` if it is outside of the prefix or not a valid path.
+    fn resolve(&self, path: &str) -> Option<PathBuf> {
+        let path = path.strip_prefix(&self.prefix)?;
+        if !path.is_empty() && !path.starts_with('/') {
+            return None;
+        }
+        let path = percent_decode_str(path).decode_utf8().ok()?;
+        let mut resolved = self.root.clone();
+        for segment in path.split('/') {
+            match segment {
+                "" | "." => {}
+                ".." => return None,
+                s if s.contains(['\\', '\0']) => return None,
+                s => resolved.push(s),
+            }
+        }
+        Some(resolved)
+    }
+
+    /// Answer the request with the file its path maps to.
+    pub fn serve(&self, req: &Request) -> Result<Response, ErrorCode> {
+        if let Some(resp) = method_not_allowed(req) {
+            return resp;
+        }
+        let Some(mut path) = self.resolve(req.path()) else {
+            return status(404);
+        };
+        let metadata = match fs::metadata(&path) {
+            Ok(metadata) => metadata,
+            Err(e) => return error_status(&e),
+        };
+        if metadata.is_dir() {
+            // relative links of the index file only work under a trailing slash
+            if !req.path().ends_with('/') {
+                let location = match req.uri.path_and_query.as_ref().and_then(|p| p.query()) {
+                    Some(query) => format!("{}/?{query}", req.path()),
+                    None => format!("{}/", req.path()),
+                };
+                return Response::builder()
+                    .status_code(301)
+                    .header(LOCATION, location)
+                    .build();
+            }
+            match &self.index_file {
+                Some(index_file) => path.push(index_file),
+                None => return status(404),
+            }
+        }
+        serve_file(req, &path, None)
+    }
+}
+
+/// Serve a single file, whatever the request path.
+///
+/// It behaves like [`ServeDir`] for methods, headers and ranges.
+#[derive(Debug, Clone)]
+pub struct ServeFile {
+    path: PathBuf,
+    content_type: Option<Mime>,
+}
+
+impl ServeFile {
+    /// Serve the file at `path`, relative to a preopened directory.
+    pub fn new<P: Into<PathBuf>>(path: P) -> Self {
+        Self {
+            path: path.into(),
+            content_type: None,
+        }
+    }
+
+    /// Set the `Content-Type` instead of guessing it from the file extension.
+    pub fn content_type(mut self, mime: Mime) -> Self {
+        self.content_type = Some(mime);
+        self
+    }
+
+    /// Answer the request with the file.
+    pub fn serve(&self, req: &Request) -> Result<Response, ErrorCode> {
+        if let Some(resp) = method_not_allowed(req) {
+            return resp;
+        }
+        serve_file(req, &self.path, self.content_type.clone())
+    }
+}
+
+#[inline]
+fn status(status_code: u16) -> Result<Response, ErrorCode> {
+    Response::builder().status_code(status_code).build()
+}
+
+fn error_status(e: &io::Error) -> Result<Response, ErrorCode> {
+    match e.kind() {
+        io::ErrorKind::PermissionDenied => status(403),
+        _ => status(404),
+    }
+}
+
+fn method_not_allowed(req: &Request) -> Option<Result<Response, ErrorCode>> {
+    match req.method {
+        Method::Get | Method::Head => None,
+        _ => Some(
+            Response::builder()
+                .status_code(405)
+                .header(ALLOW, "GET, HEAD")
+                .build(),
+        ),
+    }
+}
+
+/// Compute an entity tag from the modification time and the length of a file.
+///
+/// The tag is weak, see RFC 9110 section 8.8.3.1: two versions written within the same second
+/// may have the same length, so `If-Range` falls back to the `Last-Modified` date.
+fn file_etag(modified: SystemTime, len: u64) -> Option<EntityTag> {
+    let modified = modified.duration_since(UNIX_EPOCH).ok()?;
+    Some(EntityTag::weak(format!(
+        "{:x}-{:x}",
+        modified.as_secs(),
+        len
+    )))
+}
+
+fn serve_file(
+    req: &Request,
+    path: &Path,
+    content_type: Option<Mime>,
+) -> Result<Response, ErrorCode> {
+    let (file, metadata) = match File::open(path).and_then(|f| {
+        let metadata = f.metadata()?;
+        Ok((f, metadata))
+    }) {
+        Ok((file, metadata)) if metadata.is_file() => (file, metadata),
+        Ok(_) => return status(404),
+        Err(e) => return error_status(&e),
+    };
+    respond_with_file(req, path, file, &metadata, content_type)
+}
+
+fn respond_with_file(
+    req: &Request,
+    path: &Path,
+    file: File,
+    metadata: &Metadata,
+    content_type: Option<Mime>,
+) -> Result<Response, ErrorCode> {
+    let len = metadata.len();
+    let content_type =
+        content_type.unwrap_or_else(|| mime_guess::from_path(path).first_or_octet_stream());
+    let mut ranged = Ranged::new(file, len).content_type(content_type);
+    if let Ok(modified) = metadata.modified() {
+        if let Some(etag) = file_etag(modified, len) {
+            ranged = ranged.etag(etag);
+        }
+        ranged = ranged.last_modified(modified);
+    }
+    ranged.respond(req)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_resolve() {
+        let dir = ServeDir::new("public").prefix("/static/");
+        assert_eq!(
+            dir.resolve("/static/css/main%20v2.css"),
+            Some(PathBuf::from("public/css/main v2.css"))
+        );
+        assert_eq!(dir.resolve("/static"), Some(PathBuf::from("public")));
+        assert_eq!(
+            dir.resolve("/static/./a//b"),
+            Some(PathBuf::from("public/a/b"))
+        );
+        assert_eq!(dir.resolve("/static/../secret"), None);
+        assert_eq!(dir.resolve("/static/%2e%2e/secret"), None);
+        assert_eq!(dir.resolve("/static/a%5c..%5csecret"), None);
+        assert_eq!(dir.resolve("/staticfile"), None);
+        assert_eq!(dir.resolve("/other"), None);
+    }
+}
@@ -0,0 +1,421 @@
+//! Streaming JSON bodies.
+//!
+//! Newline delimited JSON (NDJSON, also known as JSON Lines) bodies are read one record at a time
+//! with `json_lines()` on a request or response, and written lazily from an iterator with
+//! `json_lines()` on a builder. The elements of a body made of a single large JSON array are read
+//! one at a time with `json_array_stream()`.
+
+use crate::body::{Body, BodyLimitExceeded, BodyReader};
+
+use anyhow::{anyhow, Result};
+use serde::{de::DeserializeOwned, Serialize};
+use std::io::{self, BufRead, Read};
+use std::marker::PhantomData;
+
+/// The content type of newline delimited JSON bodies.
+pub(crate) const NDJSON: &str = "application/x-ndjson";
+
+/// An iterator over the records of a newline delimited JSON body, see
+/// [`Response::json_lines`](crate::Response::json_lines).
+///
+/// Blank lines are skipped. A record that fails to deserialize is returned as an error and the
+/// next call moves on to the following line, while a failure to read the body ends the iteration,
+/// as does an incoming line larger than the body limit with [`BodyLimitExceeded`].
+pub struct JsonLines<T> {
+    reader: BodyReader,
+    line: Vec<u8>,
+    exceeded: Option<BodyLimitExceeded>,
+    done: bool,
+    _marker: PhantomData<fn() -> T>,
+}
+
+impl<T> JsonLines<T> {
+    pub(crate) fn new(body: Body) -> Self {
+        Self {
+            exceeded: body.limit_exceeded(),
+            reader: BodyReader::new(body),
+            line: vec![],
+            done: false,
+            _marker: PhantomData,
+        }
+    }
+}
+
+impl<T: DeserializeOwned> Iterator for JsonLines<T> {
+    type Item = Result<T>;
+
+    fn next(&mut self) -> Option<Self::Item> {
+        while !self.done {
+            self.line.clear();
+            // a line may not be larger than the body limit
+            let read = match self.exceeded {
+                Some(e) => (&mut self.reader)
+                    .take(e.limit() + 1)
+                    .read_until(b'\n', &mut self.line),
+                None => self.reader.read_until(b'\n', &mut self.line),
+            };
+            match read {
+                Ok(0) => self.done = true,
+                Ok(_)
+                    if self
+                        .exceeded
+                        .is_some_and(|e| self.line.len() as u64 > e.limit()) =>
+                {
+                    self.done = true;
+                    return self.exceeded.map(|e| Err(e.into()));
+                }
+                Ok(_) => {
+                    let line = self.line.trim_ascii();
+                    if !line.is_empty() {
+                        return Some(serde_json::from_slice(line).map_err(Into::into));
+                    }
+                }
+                Err(e) => {
+                    self.done = true;
+                    return Some(Err(e.into()));
+                }
+            }
+        }
+        None
+    }
+}
+
+/// Deserialize a JSON body as it is read, without holding its raw bytes in memory.
+pub(crate) fn from_body<T: DeserializeOwned>(body: Body) -> Result<T> {
+    match body {
+        Body::Bytes(data) => Ok(serde_json::from_slice(&data)?),
+        body => serde_json::from_reader(body.into_limited_reader()?).map_err(|e| {
+            if !e.is_io() {
+                return e.into();
+            }
+            // keep the limit error downcastable instead of wrapping it in a JSON error
+            let e = io::Error::from(e);
+            match e
+                .get_ref()
+                .and_then(|e| e.downcast_ref::<BodyLimitExceeded>())
+            {
+                Some(exceeded) => (*exceeded).into(),
+                None => e.into(),
+            }
+        }),
+    }
+}
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+enum ArrayState {
+    Start,
+    Elements,
+    /// The closing bracket was read, only whitespace may follow.
+    End,
+    Done,
+}
+
+/// An iterator over the elements of a body made of a top-level JSON array, see
+/// [`Response::json_array_stream`](crate::Response::json_array_stream).
+///
+/// Only one element is held in memory at a time, an incoming element larger than the body limit
+/// failing with [`BodyLimitExceeded`]. An element that fails to deserialize is returned as an
+/// error and the next call moves on to the following one, while a body that is not a well-formed
+/// array ends the iteration with an error.
+pub struct JsonArrayStream<T> {
+    reader: BodyReader,
+    state: ArrayState,
+    element: Vec<u8>,
+    exceeded: Option<BodyLimitExceeded>,
+    _marker: PhantomData<fn() -> T>,
+}
+
+impl<T> JsonArrayStream<T> {
+    pub(crate) fn new(body: Body) -> Self {
+        Self {
+            exceeded: body.limit_exceeded(),
+            reader: BodyReader::new(body),
+            state: ArrayState::Start,
+            element: vec![],
+            _marker: PhantomData,
+        }
+    }
+
+    /// Skip whitespace and peek the next byte, `None` at the end of the body.
+    fn peek(&mut self) -> io::Result<Option<u8>> {
+        loop {
+            let available = self.reader.fill_buf()?;
+            if available.is_empty() {
+                return Ok(None);
+            }
+            match available.iter().position(|b| !b.is_ascii_whitespace()) {
+                Some(i) => {
+                    let b = available[i];
+                    self.reader.consume(i);
+                    return Ok(Some(b));
+                }
+                None => {
+                    let n = available.len();
+                    self.reader.consume(n);
+                }
+            }
+        }
+    }
+
+    /// Read the opening bracket, returning whether the array is empty.
+    fn start(&mut self) -> Result<bool> {
+        if self.peek()? != Some(b'[') {
+            return Err(anyhow!("the body is not a JSON array"));
+        }
+        self.reader.consume(1);
+        if self.peek()? == Some(b']') {
+            self.reader.consume(1);
+            return Ok(true);
+        }
+        Ok(false)
+    }
+
+    /// Read the raw bytes of the next element up to the following `,` or `]`, returning whether
+    /// it is the last one.
+    fn scan_element(&mut self) -> Result<bool> {
+        self.element.clear();
+        let mut depth = 0usize;
+        let mut in_string = false;
+        let mut escaped = false;
+        loop {
+            let available = self.reader.fill_buf()?;
+            if available.is_empty() {
+                return Err(anyhow!("unexpected end of the JSON array"));
+            }
+            let mut end = None;
+            for (i, &b) in available.iter().enumerate() {
+                if in_string {
+                    if escaped {
+                        escaped = false;
+                    } else if b == b'\\' {
+                        escaped = true;
+                    } else if b == b'"' {
+                        in_string = false;
+                    }
+                    continue;
+                }
+                match b {
+                    b'"' => in_string = true,
+                    b'[' | b'{' => depth += 1,
+                    b',' if depth == 0 => {
+                        end = Some((i, false));
+                        break;
+                    }
+                    b']' if depth == 0 => {
+                        end = Some((i, true));
+                        break;
+                    }
+                    b']' | b'}' => {
+                        depth = depth
+                            .checked_sub(1)
+                            .ok_or_else(|| anyhow!("unbalanced brackets in the JSON array"))?;
+                    }
+                    _ => {}
+                }
+            }
+            match end {
+                Some((i, last)) => {
+                    self.element.extend_from_slice(&available[..i]);
+                    self.reader.consume(i + 1);
+                    self.check_limit()?;
+                    return Ok(last);
+                }
+                None => {
+                    let n = available.len();
+                    self.element.extend_from_slice(available);
+                    self.reader.consume(n);
+                    self.check_limit()?;
+                }
+            }
+        }
+    }
+
+    fn check_limit(&self) -> Result<()> {
+        match self.exceeded {
+            Some(e) if self.element.len() as u64 > e.limit() => Err(e.into()),
+            _ => Ok(()),
+        }
+    }
+
+    /// Check that only whitespace follows the closing bracket.
+    fn end(&mut self) -> Result<()> {
+        match self.peek()? {
+            None => Ok(()),
+            Some(_) => Err(anyhow!("unexpected data after the JSON array")),
+        }
+    }
+}
+
+impl<T: DeserializeOwned> Iterator for JsonArrayStream<T> {
+    type Item = Result<T>;
+
+    fn next(&mut self) -> Option<Self::Item> {
+        if self.state == ArrayState::Start {
+            match self.start() {
+                Ok(empty) => {
+                    self.state = match empty {
+                        true => ArrayState::End,
+                        false => ArrayState::Elements,
+                    }
+                }
+                Err(e) => {
+                    self.state = ArrayState::Done;
+                    return Some(Err(e));
+                }
+            }
+        }
+        match self.state {
+            ArrayState::End => {
+                self.state = ArrayState::Done;
+                return self.end().err().map(Err);
+            }
+            ArrayState::Done => return None,
+            _ => {}
+        }
+        match self.scan_element() {
+            Ok(last) => {
+                if last {
+                    self.state = ArrayState::End;
+                }
+                Some(serde_json::from_slice(&self.element).map_err(Into::into))
+            }
+            Err(e) => {
+                self.state = ArrayState::Done;
+                Some(Err(e))
+            }
+        }
+    }
+}
+
+/// A reader serializing the values of an iterator as newline delimited JSON, one at a time.
+pub(crate) struct JsonLinesReader<I> {
+    iter: I,
+    buf: Vec<u8>,
+    pos: usize,
+}
+
+impl<I> JsonLinesReader<I> {
+    pub(crate) fn new(iter: I) -> Self {
+        Self {
+            iter,
+            buf: vec![],
+            pos: 0,
+        }
+    }
+}
+
+impl<I> Read for JsonLinesReader<I>
+where
+    I: Iterator,
+    I::Item: Serialize,
+{
+    fn read(&mut self, buf: &mut [u8]) -> io::Result<usize> {
+        while self.pos >= self.buf.len() {
+            match self.iter.next() {
+                Some(value) => {
+                    self.buf.clear();
+                    self.pos = 0;
+                    // drop a partially serialized value, so that it is never read
+                    serde_json::to_writer(&mut self.buf, &value)
+                        .inspect_err(|_| self.buf.clear())?;
+                    self.buf.push(b'\n');
+                }
+                None => return Ok(0),
+            }
+        }
+        let n = (self.buf.len() - self.pos).min(buf.len());
+        buf[..n].copy_from_slice(&self.buf[self.pos..self.pos + n]);
+        self.pos += n;
+        Ok(n)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use serde::Deserialize;
+
+    #[derive(Debug, PartialEq, Serialize, Deserialize)]
+    struct Record {
+        id: u32,
+    }
+
+    #[test]
+    fn test_json_lines() -> Result<()> {
+        let mut body = String::new();
+        JsonLinesReader::new((1..=3).map(|id| Record { id })).read_to_string(&mut body)?;
+        assert_eq!(body, "{\"id\":1}\n{\"id\":2}\n{\"id\":3}\n");
+
+        let body = Body::Bytes(b"{\"id\":1}\r\n\n{\"id\":\"2\"}\n  {\"id\":3}".to_vec());
+        let mut records = JsonLines::<Record>::new(body);
+        assert_eq!(records.next().unwrap()?, Record { id: 1 });
+        assert!(records.next().unwrap().is_err());
+        assert_eq!(records.next().unwrap()?, Record { id: 3 });
+        assert!(records.next().is_none());
+        Ok(())
+    }
+
+    #[test]
+    fn test_json_lines_errors() -> Result<()> {
+        struct Failing(bool);
+
+        impl Serialize for Failing {
+            fn serialize<S: serde::Serializer>(&self, serializer: S) -> Result<S::Ok, S::Error> {
+                use serde::ser::{Error, SerializeMap};
+                let mut map = serializer.serialize_map(None)?;
+                map.serialize_entry("id", &1)?;
+                if self.0 {
+                    return Err(S::Error::custom("failed"));
+                }
+                map.end()
+            }
+        }
+
+        // a value failing to serialize is not partially written
+        let mut reader = JsonLinesReader::new([Failing(true), Failing(false)].into_iter());
+        let mut buf = [0; 64];
+        assert!(reader.read(&mut buf).is_err());
+        let n = reader.read(&mut buf)?;
+        assert_eq!(&buf[..n], b"{\"id\":1}\n");
+        Ok(())
+    }
+
+    #[test]
+    fn test_json_array_stream() -> Result<()> {
+        let body = Body::Bytes(
+            br#" [ {"id": 1, "tags": ["a,]", "\"}"]}, {"id": "2"} ,{"id":3}] "#.to_vec(),
+        );
+        let mut records = JsonArrayStream::<serde_json::Value>::new(body);
+        assert_eq!(records.next().unwrap()?["tags"][0], "a,]");
+        assert_eq!(records.next().unwrap()?["id"], "2");
+        assert_eq!(records.next().unwrap()?["id"], 3);
+        assert!(records.next().is_none());
+
+        let mut records = JsonArrayStream::<Record>::new(Body::Bytes(b"[1, 2".to_vec()));
+        assert!(records.next().unwrap().is_err());
+        assert!(records.next().unwrap().is_err());
+        assert!(records.next().is_none());
+
+        assert!(
+            JsonArrayStream::<Record>::new(Body::Bytes(b" [ ] ".to_vec()))
+                .next()
+                .is_none()
+        );
+        assert!(JsonArrayStream::<Record>::new(Body::Bytes(b"{}".to_vec()))
+            .next()
+            .unwrap()
+            .is_err());
+
+        let mut records = JsonArrayStream::<Record>::new(Body::Bytes(b"[}]".to_vec()));
+        assert!(records.next().unwrap().is_err());
+        assert!(records.next().is_none());
+
+        let mut records = JsonArrayStream::<Record>::new(Body::Bytes(b"[{\"id\":1}] x".to_vec()));
+        assert_eq!(records.next().unwrap()?, Record { id: 1 });
+        assert!(records.next().unwrap().is_err());
+        assert!(records.next().is_none());
+        let mut records = JsonArrayStream::<Record>::new(Body::Bytes(b"[]]".to_vec()));
+        assert!(records.next().unwrap().is_err());
+        Ok(())
+    }
+}
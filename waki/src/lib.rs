@@ -32,13 +32,33 @@
 
 #![cfg_attr(docsrs, feature(doc_auto_cfg))]
 
+pub mod auth;
 mod body;
+#[cfg(feature = "cache")]
+pub mod cache;
 mod client;
 mod common;
+mod conditional;
+mod download;
+#[cfg(feature = "fs")]
+pub mod fs;
+#[cfg(feature = "json")]
+pub mod json;
+#[cfg(feature = "jwt")]
+pub mod jwt;
 #[cfg(feature = "multipart")]
 pub mod multipart;
+mod negotiate;
+mod pending;
+mod proxy;
+mod range;
 mod request;
 mod response;
+#[cfg(feature = "async")]
+pub mod rt;
+#[cfg(feature = "signing")]
+pub mod sign;
+mod typed_header;
 
 #[doc(hidden)]
 pub mod bindings {
@@ -50,14 +70,24 @@ pub mod bindings {
     });
 }
 
+#[cfg(feature = "async")]
+pub use self::body::BodyWriter;
+#[cfg(feature = "async")]
 #[doc(hidden)]
-pub use self::response::handle_response;
+pub use self::response::handle_response_async;
 pub use self::{
     bindings::wasi::http::types::{ErrorCode, Method},
+    body::{set_default_body_limit, BodyLimitExceeded, BodyReader},
     client::Client,
+    download::{Checksum, Download},
+    pending::{Join, PendingResponse},
+    range::{ContentRange, Ranged},
     request::{Request, RequestBuilder},
     response::{Response, ResponseBuilder},
+    typed_header::{Authorization, EntityTag, IfMatch, IfNoneMatch, QualityItem},
 };
+#[doc(hidden)]
+pub use self::{conditional::Preconditions, response::handle_response};
 
 /// Export the annotated function as entrypoint of the WASI HTTP component.
 ///
@@ -73,6 +103,24 @@ pub use self::{
 ///     Response::builder().body(b"Hello, WASI!").build()
 /// }
 /// ```
+///
+/// With the `async` feature enabled, the function can also be `async`, it then runs on the
+/// executor in [`rt`](crate::rt):
+///
+/// ```ignore
+/// use waki::{handler, Client, ErrorCode, Request, Response};
+///
+/// #[handler]
+/// async fn hello(req: Request) -> Result<Response, ErrorCode> {
+///     let resp = Client::new()
+///         .get("https://httpbin.org/get")
+///         .send_async()
+///         .await
+///         .map_err(|e| ErrorCode::InternalError(Some(e.to_string())))?;
+///     Response::builder().body(resp.body().unwrap()).build()
+/// }
+/// ```
 pub use waki_macros::handler;
 
 pub use http::header;
+pub use mime;
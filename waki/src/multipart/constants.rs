@@ -1,4 +1,7 @@
 pub const MAX_HEADERS: usize = 32;
+pub const MAX_DEPTH: usize = 16;
+/// The maximum size of a preamble or a header block.
+pub const MAX_HEAD_SIZE: usize = 16 * 1024;
 pub const BOUNDARY_EXT: &str = "--";
 pub const CRLF: &str = "\r\n";
 pub const CRLF_CRLF: &str = "\r\n\r\n";
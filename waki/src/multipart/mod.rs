@@ -1,11 +1,21 @@
 mod constants;
 pub(crate) mod parser;
 
-use crate::header::{HeaderMap, HeaderValue, IntoHeaderName, CONTENT_DISPOSITION, CONTENT_TYPE};
+pub use parser::{MultipartReader, PartReader};
+
+use crate::{
+    auth::quote,
+    header::{
+        HeaderMap, HeaderName, HeaderValue, IntoHeaderName, CONTENT_DISPOSITION, CONTENT_RANGE,
+        CONTENT_TYPE,
+    },
+    range::ContentRange,
+};
 
 use anyhow::{Error, Result};
 use mime::Mime;
 use rand::{distributions::Alphanumeric, thread_rng, Rng};
+use std::fmt;
 use std::fs::File;
 use std::io::Read;
 use std::path::Path;
@@ -25,7 +35,7 @@ impl Form {
     pub fn new() -> Self {
         Self {
             parts: vec![],
-            boundary: format!("--FormBoundary{}", generate_random_string(10)),
+            boundary: generate_boundary(),
         }
     }
 
@@ -100,6 +110,15 @@ impl Form {
     }
 }
 
+fn generate_boundary() -> String {
+    format!("--FormBoundary{}", generate_random_string(10))
+}
+
+/// Whether a byte may appear in an unquoted parameter value, see RFC 9110 section 5.6.2.
+fn is_token(b: u8) -> bool {
+    b.is_ascii_alphanumeric() || b"!#$%&'*+-.^_`|~".contains(&b)
+}
+
 fn generate_random_string(length: usize) -> String {
     thread_rng()
         .sample_iter(&Alphanumeric)
@@ -355,7 +374,7 @@ impl StreamingForm {
     pub fn new() -> Self {
         Self {
             parts: vec![],
-            boundary: format!("--FormBoundary{}", generate_random_string(10)),
+            boundary: generate_boundary(),
         }
     }
 
@@ -534,3 +553,322 @@ impl Read for StreamingFormReader {
         }
     }
 }
+
+// ============================================================================
+// Generic Multipart Support
+// ============================================================================
+
+/// The subtype of a multipart body, e.g. the `mixed` in `multipart/mixed`.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub enum Subtype {
+    /// `multipart/form-data`, see [`Form`] for building named fields.
+    FormData,
+    /// `multipart/mixed`, commonly used by batch APIs.
+    Mixed,
+    /// `multipart/alternative`
+    Alternative,
+    /// `multipart/related`, e.g. JSON metadata and binary content in one upload.
+    Related,
+    /// `multipart/byteranges`, used by responses to multi-range requests.
+    ByteRanges,
+    Other(String),
+}
+
+impl Subtype {
+    pub fn as_str(&self) -> &str {
+        match self {
+            Subtype::FormData => "form-data",
+            Subtype::Mixed => "mixed",
+            Subtype::Alternative => "alternative",
+            Subtype::Related => "related",
+            Subtype::ByteRanges => "byteranges",
+            Subtype::Other(s) => s,
+        }
+    }
+}
+
+impl From<&str> for Subtype {
+    fn from(s: &str) -> Self {
+        match s.to_ascii_lowercase().as_str() {
+            "form-data" => Subtype::FormData,
+            "mixed" => Subtype::Mixed,
+            "alternative" => Subtype::Alternative,
+            "related" => Subtype::Related,
+            "byteranges" => Subtype::ByteRanges,
+            other => Subtype::Other(other.to_string()),
+        }
+    }
+}
+
+impl fmt::Display for Subtype {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        f.write_str(self.as_str())
+    }
+}
+
+/// The content of a [`BodyPart`].
+pub enum PartBody {
+    Bytes(Vec<u8>),
+    /// A nested multipart body, e.g. a `multipart/alternative` inside a `multipart/mixed`.
+    Multipart(Multipart),
+}
+
+/// A single part of a [`Multipart`] body, described only by its headers.
+pub struct BodyPart {
+    pub headers: HeaderMap,
+    pub body: PartBody,
+}
+
+impl BodyPart {
+    pub fn new<V: Into<Vec<u8>>>(value: V) -> Self {
+        Self {
+            headers: HeaderMap::new(),
+            body: PartBody::Bytes(value.into()),
+        }
+    }
+
+    /// Create a part containing a nested multipart body.
+    ///
+    /// The `Content-Type` header of the part is set to the one of the nested body. An invalid one
+    /// is reported when the outer body is set with `multipart_body()`.
+    pub fn nested(multipart: Multipart) -> Self {
+        let mut headers = HeaderMap::new();
+        if let Ok(v) = multipart.content_type().parse() {
+            headers.insert(CONTENT_TYPE, v);
+        }
+        Self {
+            headers,
+            body: PartBody::Multipart(multipart),
+        }
+    }
+
+    /// Create a part of a `multipart/byteranges` body.
+    pub fn byterange<V: Into<Vec<u8>>>(value: V, range: ContentRange) -> Self {
+        let mut part = Self::new(value);
+        part.headers
+            .insert(CONTENT_RANGE, range.to_string().parse().unwrap());
+        part
+    }
+
+    pub fn mime(mut self, mime: Mime) -> Self {
+        self.headers
+            .insert(CONTENT_TYPE, mime.as_ref().parse().unwrap());
+        self
+    }
+
+    pub fn mime_str(self, mime: &str) -> Result<Self> {
+        Ok(self.mime(mime.parse()?))
+    }
+
+    /// Set the `Content-ID` header, used to reference parts of a `multipart/related` body.
+    pub fn content_id<V>(mut self, id: V) -> Result<Self>
+    where
+        V: TryInto<HeaderValue>,
+        <V as TryInto<HeaderValue>>::Error: Into<Error>,
+    {
+        self.headers.insert(
+            HeaderName::from_static("content-id"),
+            id.try_into().map_err(|e| e.into())?,
+        );
+        Ok(self)
+    }
+
+    pub fn headers<K, V, I>(mut self, headers: I) -> Result<Self>
+    where
+        K: IntoHeaderName,
+        V: TryInto<HeaderValue>,
+        <V as TryInto<HeaderValue>>::Error: Into<Error>,
+        I: IntoIterator<Item = (K, V)>,
+    {
+        for (key, value) in headers.into_iter() {
+            self.headers
+                .insert(key, value.try_into().map_err(|e| e.into())?);
+        }
+        Ok(self)
+    }
+
+    /// Get the parsed `Content-Type` header of the part.
+    pub fn content_type(&self) -> Option<Mime> {
+        self.headers.get(CONTENT_TYPE)?.to_str().ok()?.parse().ok()
+    }
+
+    /// Get the parsed `Content-Range` header of a `multipart/byteranges` part.
+    pub fn content_range(&self) -> Option<ContentRange> {
+        self.headers.get(CONTENT_RANGE)?.to_str().ok()?.parse().ok()
+    }
+
+    /// Get the content of the part, `None` if it is a nested multipart body.
+    pub fn bytes(&self) -> Option<&[u8]> {
+        match &self.body {
+            PartBody::Bytes(bytes) => Some(bytes),
+            PartBody::Multipart(_) => None,
+        }
+    }
+
+    fn write_to(self, buf: &mut Vec<u8>) {
+        for (k, v) in self.headers.iter() {
+            buf.extend_from_slice(format!("{}: ", k).as_bytes());
+            buf.extend_from_slice(v.as_bytes());
+            buf.extend_from_slice(constants::CRLF.as_bytes());
+        }
+        buf.extend_from_slice(constants::CRLF.as_bytes());
+        match self.body {
+            PartBody::Bytes(bytes) => buf.extend_from_slice(&bytes),
+            PartBody::Multipart(multipart) => buf.extend_from_slice(&multipart.build()),
+        }
+    }
+}
+
+/// A multipart body of any subtype.
+///
+/// ```
+/// # use anyhow::Result;
+/// # fn run() -> Result<()> {
+/// use waki::multipart::{BodyPart, Multipart};
+///
+/// let related = Multipart::related()
+///     .param("type", "application/json")
+///     .part(BodyPart::new(r#"{"name": "photo.png"}"#).mime(mime::APPLICATION_JSON))
+///     .part(BodyPart::new(vec![0x89, 0x50, 0x4e, 0x47]).mime(mime::IMAGE_PNG));
+///
+/// let mixed = Multipart::mixed()
+///     .part(BodyPart::new("hello").mime(mime::TEXT_PLAIN))
+///     .part(BodyPart::nested(related));
+/// # Ok(())
+/// # }
+/// ```
+pub struct Multipart {
+    subtype: Subtype,
+    boundary: String,
+    params: Vec<(String, String)>,
+    parts: Vec<BodyPart>,
+}
+
+impl Multipart {
+    pub fn new(subtype: Subtype) -> Self {
+        Self::with_boundary(subtype, generate_boundary())
+    }
+
+    pub(crate) fn with_boundary(subtype: Subtype, boundary: String) -> Self {
+        Self {
+            subtype,
+            boundary,
+            params: vec![],
+            parts: vec![],
+        }
+    }
+
+    /// Create a `multipart/mixed` body.
+    pub fn mixed() -> Self {
+        Self::new(Subtype::Mixed)
+    }
+
+    /// Create a `multipart/related` body.
+    ///
+    /// The media type of the root part should be set with the `type` parameter.
+    pub fn related() -> Self {
+        Self::new(Subtype::Related)
+    }
+
+    /// Create a `multipart/byteranges` body.
+    pub fn byteranges() -> Self {
+        Self::new(Subtype::ByteRanges)
+    }
+
+    pub fn subtype(&self) -> &Subtype {
+        &self.subtype
+    }
+
+    pub fn boundary(&self) -> &str {
+        &self.boundary
+    }
+
+    /// Get a parameter of the `Content-Type` header, e.g. `type` or `start` of `multipart/related`.
+    pub fn get_param(&self, name: &str) -> Option<&str> {
+        self.params
+            .iter()
+            .find(|(k, _)| k.eq_ignore_ascii_case(name))
+            .map(|(_, v)| v.as_str())
+    }
+
+    /// Add a parameter to the `Content-Type` header.
+    pub fn param<K: Into<String>, V: Into<String>>(mut self, name: K, value: V) -> Self {
+        self.params.push((name.into(), value.into()));
+        self
+    }
+
+    pub fn part(mut self, part: BodyPart) -> Self {
+        self.parts.push(part);
+        self
+    }
+
+    /// Add a nested multipart body as a part.
+    pub fn nested(self, multipart: Multipart) -> Self {
+        self.part(BodyPart::nested(multipart))
+    }
+
+    pub fn parts(&self) -> &[BodyPart] {
+        &self.parts
+    }
+
+    pub fn into_parts(self) -> Vec<BodyPart> {
+        self.parts
+    }
+
+    /// The value of the `Content-Type` header for this body.
+    ///
+    /// Parameter values are quoted, with their backslashes and double quotes escaped.
+    pub fn content_type(&self) -> String {
+        let boundary = match self.boundary.bytes().all(is_token) {
+            true => self.boundary.clone(),
+            false => quote(&self.boundary),
+        };
+        let mut content_type = format!("multipart/{}; boundary={}", self.subtype, boundary);
+        for (k, v) in &self.params {
+            content_type.push_str(&format!("; {}={}", k, quote(v)));
+        }
+        content_type
+    }
+
+    /// Check that the `Content-Type` headers of this body and its nested bodies are valid.
+    pub(crate) fn check(&self) -> Result<HeaderValue> {
+        for part in &self.parts {
+            if let PartBody::Multipart(ref multipart) = part.body {
+                multipart.check()?;
+            }
+        }
+        Ok(self.content_type().parse()?)
+    }
+
+    pub fn build(self) -> Vec<u8> {
+        let mut buf = vec![];
+        for part in self.parts {
+            buf.extend_from_slice(
+                format!(
+                    "{}{}{}",
+                    constants::BOUNDARY_EXT,
+                    self.boundary,
+                    constants::CRLF
+                )
+                .as_bytes(),
+            );
+            part.write_to(&mut buf);
+            buf.extend_from_slice(constants::CRLF.as_bytes());
+        }
+        buf.extend_from_slice(
+            format!(
+                "{}{}{}",
+                constants::BOUNDARY_EXT,
+                self.boundary,
+                constants::BOUNDARY_EXT,
+            )
+            .as_bytes(),
+        );
+        buf
+    }
+
+    /// Parse a multipart body given the value of its `Content-Type` header.
+    pub fn parse(content_type: &str, body: &[u8]) -> Result<Self> {
+        parser::parse_multipart(&content_type.parse()?, body)
+    }
+}
@@ -1,12 +1,18 @@
 use crate::{
-    header::{HeaderMap, HeaderName, HeaderValue, CONTENT_DISPOSITION, CONTENT_TYPE},
-    multipart::{constants, Part},
+    body::{Body, BodyReader},
+    header::{
+        HeaderMap, HeaderName, HeaderValue, CONTENT_DISPOSITION, CONTENT_RANGE, CONTENT_TYPE,
+    },
+    multipart::{constants, BodyPart, Multipart, Part, PartBody, Subtype},
+    range::ContentRange,
 };
 
 use anyhow::{anyhow, Result};
 use bytes::{Buf, Bytes, BytesMut};
 use httparse::Status;
+use mime::Mime;
 use std::collections::HashMap;
+use std::io::{self, BufRead, Read};
 
 struct Buffer {
     buf: BytesMut,
@@ -17,17 +23,24 @@ impl Buffer {
         Self { buf: data.into() }
     }
 
+    fn len(&self) -> usize {
+        self.buf.len()
+    }
+
+    fn extend(&mut self, data: &[u8]) {
+        self.buf.extend_from_slice(data)
+    }
+
     fn peek_exact(&mut self, size: usize) -> Option<&[u8]> {
         self.buf.get(..size)
     }
 
-    fn read_until(&mut self, pattern: &[u8]) -> Option<Bytes> {
+    fn find(&self, pattern: &[u8]) -> Option<usize> {
         memchr::memmem::find(&self.buf, pattern)
-            .map(|idx| self.buf.split_to(idx + pattern.len()).freeze())
     }
 
-    fn read_to(&mut self, pattern: &[u8]) -> Option<Bytes> {
-        memchr::memmem::find(&self.buf, pattern).map(|idx| self.buf.split_to(idx).freeze())
+    fn read_exact(&mut self, n: usize) -> Bytes {
+        self.buf.split_to(n).freeze()
     }
 
     fn advance(&mut self, n: usize) {
@@ -35,83 +48,348 @@ impl Buffer {
     }
 }
 
-pub fn parse(body: &[u8], boundary: &str) -> Result<HashMap<String, Part>> {
-    let mut buffer = Buffer::new(body);
-    let boundary = format!("{}{}", constants::BOUNDARY_EXT, boundary);
+#[derive(PartialEq)]
+enum State {
+    /// Looking for the first boundary
+    Preamble,
+    /// Positioned at the headers of the next part
+    PartHeaders,
+    /// Inside the content of the current part
+    PartContent,
+    /// Right after the delimiter closing the content of a part
+    Delimiter,
+    /// The close delimiter has been reached
+    Finished,
+}
 
-    // Finding the first boundary
-    if buffer
-        .read_until(format!("{}{}", boundary, constants::CRLF).as_bytes())
-        .is_none()
-    {
-        return Err(anyhow!("incomplete multipart data, missing boundary"));
-    };
+/// A streaming multipart parser, reading parts from a body as they arrive.
+///
+/// The full body is never buffered: only the bytes needed to find the next boundary are kept in
+/// memory, and the content of each part is exposed through a [`PartReader`]. A preamble or a
+/// header block larger than 16 KiB is an error.
+///
+/// ```
+/// # use anyhow::Result;
+/// # use std::io::Read;
+/// # use waki::Response;
+/// # fn run() -> Result<()> {
+/// # let resp = Response::new();
+/// let mut parts = resp.multipart_reader()?;
+/// while let Some(mut part) = parts.next_part()? {
+///     println!("content-type: {:?}", part.content_type());
+///     let mut content = vec![];
+///     part.read_to_end(&mut content)?;
+/// }
+/// # Ok(())
+/// # }
+/// ```
+pub struct MultipartReader {
+    body: BodyReader,
+    buffer: Buffer,
+    content_type: Mime,
+    /// `--boundary`
+    dash_boundary: Vec<u8>,
+    /// `\r\n--boundary`, which ends the content of a part
+    delimiter: Vec<u8>,
+    state: State,
+}
 
-    let mut parts = HashMap::new();
+impl MultipartReader {
+    pub(crate) fn new(content_type: Mime, body: Body) -> Result<Self> {
+        if content_type.type_() != mime::MULTIPART {
+            return Err(anyhow!("{content_type} is not a multipart media type"));
+        }
+        let dash_boundary = format!("{}{}", constants::BOUNDARY_EXT, boundary(&content_type)?);
+        let delimiter = format!("{}{}", constants::CRLF, dash_boundary);
 
-    loop {
-        // Finding headers
-        let header_bytes = match buffer.read_until(constants::CRLF_CRLF.as_bytes()) {
-            Some(bytes) => bytes,
-            None => return Err(anyhow!("incomplete multipart data, missing headers")),
-        };
+        Ok(Self {
+            body: BodyReader::new(body),
+            buffer: Buffer::new(&[]),
+            content_type,
+            dash_boundary: dash_boundary.into_bytes(),
+            delimiter: delimiter.into_bytes(),
+            state: State::Preamble,
+        })
+    }
+
+    /// The subtype of the multipart body.
+    pub fn subtype(&self) -> Subtype {
+        self.content_type.subtype().as_str().into()
+    }
+
+    /// Get a parameter of the `Content-Type` header of the body.
+    pub fn get_param(&self, name: &str) -> Option<&str> {
+        self.content_type.get_param(name).map(|v| v.as_str())
+    }
+
+    /// Pull more data from the body into the buffer, returning false at the end of the body.
+    fn fill(&mut self) -> Result<bool> {
+        let chunk = self.body.fill_buf()?;
+        let n = chunk.len();
+        self.buffer.extend(chunk);
+        self.body.consume(n);
+        Ok(n > 0)
+    }
+
+    /// Read up to and including `pattern`, which must be found within
+    /// [`constants::MAX_HEAD_SIZE`] bytes.
+    fn read_until(&mut self, pattern: &[u8], err: &str) -> Result<Bytes> {
+        loop {
+            match self.buffer.find(pattern) {
+                Some(idx) if idx <= constants::MAX_HEAD_SIZE => {
+                    return Ok(self.buffer.read_exact(idx + pattern.len()));
+                }
+                None if self.buffer.len() <= constants::MAX_HEAD_SIZE => {}
+                _ => {
+                    return Err(anyhow!(
+                        "invalid multipart data, {err} within {} bytes",
+                        constants::MAX_HEAD_SIZE
+                    ))
+                }
+            }
+            if !self.fill()? {
+                return Err(anyhow!("incomplete multipart data, {err}"));
+            }
+        }
+    }
 
-        let mut part = Part::new("", vec![]);
-        let mut headers = [httparse::EMPTY_HEADER; constants::MAX_HEADERS];
-        part.headers = match httparse::parse_headers(&header_bytes, &mut headers)? {
-            Status::Complete((_, raw_headers)) => {
-                let mut headers_map = HeaderMap::with_capacity(raw_headers.len());
-                for header in raw_headers {
-                    let (k, v) = (
-                        HeaderName::try_from(header.name)?,
-                        HeaderValue::try_from(header.value)?,
-                    );
-                    if k == CONTENT_DISPOSITION {
-                        // can't parse it without a /
-                        let mime = format!("multipart/{}", v.to_str()?).parse::<mime::Mime>()?;
-                        part.key = match mime.get_param("name") {
-                            Some(name) => name.to_string(),
-                            None => {
-                                return Err(anyhow!(
-                                    "missing name field in the Content-Disposition header"
-                                ))
-                            }
-                        };
-                        part.filename = mime.get_param("filename").map(|v| v.to_string());
+    fn peek_exact(&mut self, size: usize) -> Result<Option<&[u8]>> {
+        while self.buffer.len() < size {
+            if !self.fill()? {
+                return Ok(None);
+            }
+        }
+        Ok(self.buffer.peek_exact(size))
+    }
+
+    /// Read at most `buf.len()` bytes of the content of the current part.
+    fn read_content(&mut self, buf: &mut [u8]) -> Result<usize> {
+        if self.state != State::PartContent {
+            return Ok(0);
+        }
+        loop {
+            if let Some(idx) = self.buffer.find(&self.delimiter) {
+                if idx == 0 {
+                    self.buffer.advance(self.delimiter.len());
+                    self.state = State::Delimiter;
+                    return Ok(0);
+                }
+                let n = idx.min(buf.len());
+                buf[..n].copy_from_slice(&self.buffer.read_exact(n));
+                return Ok(n);
+            }
+            // everything except a possible prefix of the delimiter can be handed out
+            let safe = self.buffer.len().saturating_sub(self.delimiter.len() - 1);
+            if safe > 0 {
+                let n = safe.min(buf.len());
+                buf[..n].copy_from_slice(&self.buffer.read_exact(n));
+                return Ok(n);
+            }
+            if !self.fill()? {
+                return Err(anyhow!("incomplete multipart data, missing field data"));
+            }
+        }
+    }
+
+    /// Advance to the next part, skipping any unread content of the current one.
+    ///
+    /// Returns `None` once the close delimiter is reached.
+    pub fn next_part(&mut self) -> Result<Option<PartReader<'_>>> {
+        loop {
+            match self.state {
+                State::Preamble => {
+                    let mut first_boundary = self.dash_boundary.clone();
+                    first_boundary.extend_from_slice(constants::CRLF.as_bytes());
+                    self.read_until(&first_boundary, "missing boundary")?;
+                    self.state = State::PartHeaders;
+                }
+                State::PartHeaders => {
+                    // a part without headers starts with an empty line
+                    let headers = if self.peek_exact(constants::CRLF.len())?
+                        == Some(constants::CRLF.as_bytes())
+                    {
+                        self.buffer.advance(constants::CRLF.len());
+                        HeaderMap::new()
+                    } else {
+                        let bytes =
+                            self.read_until(constants::CRLF_CRLF.as_bytes(), "missing headers")?;
+                        parse_headers(&bytes)?
+                    };
+                    self.state = State::PartContent;
+                    return Ok(Some(PartReader {
+                        reader: self,
+                        headers,
+                    }));
+                }
+                State::PartContent => {
+                    let mut skip = vec![0; 64 * 1024];
+                    while self.read_content(&mut skip)? > 0 {}
+                }
+                State::Delimiter => {
+                    let next_bytes = match self.peek_exact(constants::BOUNDARY_EXT.len())? {
+                        Some(bytes) => bytes,
+                        None => return Err(anyhow!("incomplete multipart data")),
                     };
-                    if k == CONTENT_TYPE {
-                        part.mime = Some(v.to_str()?.parse()?)
+                    if next_bytes == constants::BOUNDARY_EXT.as_bytes() {
+                        self.state = State::Finished;
+                    } else {
+                        // discard transport padding and \r\n.
+                        self.read_until(constants::CRLF.as_bytes(), "missing headers")?;
+                        self.state = State::PartHeaders;
                     }
-                    headers_map.insert(k, v);
                 }
-                headers_map
+                State::Finished => return Ok(None),
             }
-            Status::Partial => return Err(anyhow!("failed to parse field complete headers")),
-        };
+        }
+    }
+}
 
-        // Finding field data
-        part.value = match buffer.read_to(format!("{}{}", constants::CRLF, boundary).as_bytes()) {
-            Some(bytes) => bytes.to_vec(),
-            None => return Err(anyhow!("incomplete multipart data, missing field data")),
-        };
+/// A part of a [`MultipartReader`], streaming its content through [`Read`].
+pub struct PartReader<'a> {
+    reader: &'a mut MultipartReader,
+    headers: HeaderMap,
+}
 
-        // Determine end of stream
-        if buffer.read_until(boundary.as_bytes()).is_none() {
-            return Err(anyhow!("incomplete multipart data, missing boundary"));
-        };
-        let next_bytes = match buffer.peek_exact(constants::BOUNDARY_EXT.len()) {
-            Some(bytes) => bytes,
-            None => return Err(anyhow!("incomplete multipart data")),
-        };
+impl PartReader<'_> {
+    pub fn headers(&self) -> &HeaderMap {
+        &self.headers
+    }
+
+    /// Get the parsed `Content-Type` header of the part.
+    pub fn content_type(&self) -> Option<Mime> {
+        self.headers.get(CONTENT_TYPE)?.to_str().ok()?.parse().ok()
+    }
+
+    /// Get the parsed `Content-Range` header of a `multipart/byteranges` part.
+    pub fn content_range(&self) -> Option<ContentRange> {
+        self.headers.get(CONTENT_RANGE)?.to_str().ok()?.parse().ok()
+    }
+
+    /// Read the remaining content of the part into memory.
+    pub fn bytes(mut self) -> Result<Vec<u8>> {
+        let mut content = vec![];
+        self.read_to_end(&mut content)?;
+        Ok(content)
+    }
+
+    /// Take the headers of the part, consuming it without reading its content.
+    pub fn into_headers(self) -> HeaderMap {
+        self.headers
+    }
+}
+
+impl Read for PartReader<'_> {
+    fn read(&mut self, buf: &mut [u8]) -> io::Result<usize> {
+        self.reader.read_content(buf).map_err(io::Error::other)
+    }
+}
+
+fn boundary(content_type: &Mime) -> Result<&str> {
+    match content_type.get_param(mime::BOUNDARY) {
+        Some(v) => Ok(v.as_str()),
+        None => Err(anyhow!(
+            "unable to find the boundary value in the Content-Type header"
+        )),
+    }
+}
+
+/// Split a multipart body into the raw headers and content of each part.
+fn parse_parts(content_type: &Mime, body: Vec<u8>) -> Result<Vec<(HeaderMap, Vec<u8>)>> {
+    let mut reader = MultipartReader::new(content_type.clone(), Body::Bytes(body))?;
+    let mut parts = vec![];
+    while let Some(mut part) = reader.next_part()? {
+        let mut value = vec![];
+        part.read_to_end(&mut value)?;
+        parts.push((part.into_headers(), value));
+    }
+    Ok(parts)
+}
+
+fn parse_headers(bytes: &[u8]) -> Result<HeaderMap> {
+    let mut headers = [httparse::EMPTY_HEADER; constants::MAX_HEADERS];
+    match httparse::parse_headers(bytes, &mut headers)? {
+        Status::Complete((_, raw_headers)) => {
+            let mut headers_map = HeaderMap::with_capacity(raw_headers.len());
+            for header in raw_headers {
+                headers_map.append(
+                    HeaderName::try_from(header.name)?,
+                    HeaderValue::try_from(header.value)?,
+                );
+            }
+            Ok(headers_map)
+        }
+        Status::Partial => Err(anyhow!("failed to parse field complete headers")),
+    }
+}
 
+/// Parse a multipart/form-data body into its named fields.
+pub fn parse(content_type: &Mime, body: &[u8]) -> Result<HashMap<String, Part>> {
+    let mut parts = HashMap::new();
+    for (headers, value) in parse_parts(content_type, body.to_vec())? {
+        let mut part = Part::new("", value);
+        for (k, v) in headers.iter() {
+            if k == CONTENT_DISPOSITION {
+                // can't parse it without a /
+                let mime = format!("multipart/{}", v.to_str()?).parse::<mime::Mime>()?;
+                part.key = match mime.get_param("name") {
+                    Some(name) => name.to_string(),
+                    None => {
+                        return Err(anyhow!(
+                            "missing name field in the Content-Disposition header"
+                        ))
+                    }
+                };
+                part.filename = mime.get_param("filename").map(|v| v.to_string());
+            };
+            if k == CONTENT_TYPE {
+                part.mime = Some(v.to_str()?.parse()?)
+            }
+        }
+        part.headers = headers;
         parts.insert(part.key.clone(), part);
+    }
+    Ok(parts)
+}
+
+/// Parse a multipart body of any subtype, recursing into nested multipart parts up to
+/// [`constants::MAX_DEPTH`] levels.
+pub fn parse_multipart(content_type: &Mime, body: &[u8]) -> Result<Multipart> {
+    parse_nested(content_type, body.to_vec(), 0)
+}
+
+fn parse_nested(content_type: &Mime, body: Vec<u8>, depth: usize) -> Result<Multipart> {
+    if depth > constants::MAX_DEPTH {
+        return Err(anyhow!(
+            "multipart body nested more than {} levels deep",
+            constants::MAX_DEPTH
+        ));
+    }
+    let parts = parse_parts(content_type, body)?;
 
-        if next_bytes == constants::BOUNDARY_EXT.as_bytes() {
-            return Ok(parts);
+    let mut multipart = Multipart::with_boundary(
+        content_type.subtype().as_str().into(),
+        boundary(content_type)?.to_string(),
+    );
+    for (name, value) in content_type.params() {
+        if name != mime::BOUNDARY {
+            multipart = multipart.param(name.as_str(), value.as_str());
         }
-        // discard \r\n.
-        buffer.advance(constants::CRLF.len());
     }
+    for (headers, value) in parts {
+        let nested = match headers.get(CONTENT_TYPE) {
+            Some(v) => match v.to_str()?.parse::<Mime>() {
+                Ok(mime) if mime.type_() == mime::MULTIPART => Some(mime),
+                _ => None,
+            },
+            None => None,
+        };
+        let body = match nested {
+            Some(mime) => PartBody::Multipart(parse_nested(&mime, value, depth + 1)?),
+            None => PartBody::Bytes(value),
+        };
+        multipart = multipart.part(BodyPart { headers, body });
+    }
+    Ok(multipart)
 }
 
 #[cfg(test)]
@@ -122,7 +400,7 @@ mod tests {
     fn test_parse() -> Result<()> {
         let data = b"--boundary\r\nContent-Disposition: form-data; name=field1\r\n\r\nvalue1\r\n--boundary\r\nContent-Disposition: form-data; name=field2; filename=file.txt\r\nContent-Type: text/plain\r\n\r\nhello\r\n--boundary--";
 
-        let parts = parse(data, "boundary")?;
+        let parts = parse(&"multipart/form-data; boundary=boundary".parse()?, data)?;
         let field1 = parts.get("field1").unwrap();
         assert_eq!(field1.key, "field1");
         assert_eq!(field1.value, b"value1");
@@ -138,4 +416,170 @@ mod tests {
         assert_eq!(field2.headers.len(), 2);
         Ok(())
     }
+
+    #[test]
+    fn test_parse_quoted_boundary() -> Result<()> {
+        let data =
+            b"--a b:c\r\nContent-Disposition: form-data; name=field\r\n\r\nvalue\r\n--a b:c--";
+
+        let parts = parse(&r#"multipart/form-data; boundary="a b:c""#.parse()?, data)?;
+        assert_eq!(parts.get("field").unwrap().value, b"value");
+        Ok(())
+    }
+
+    #[test]
+    fn test_parse_multipart() -> Result<()> {
+        let data = b"preamble\r\n--outer\r\nContent-Type: text/plain\r\n\r\nhello\r\n--outer\r\nContent-Type: multipart/related; boundary=inner; type=\"application/json\"\r\n\r\n--inner\r\nContent-Type: application/json\r\nContent-ID: <meta>\r\n\r\n{}\r\n--inner\r\n\r\nno headers\r\n--inner--\r\n--outer--\r\nepilogue";
+
+        let multipart = Multipart::parse("multipart/mixed; boundary=outer", data)?;
+        assert_eq!(multipart.subtype(), &Subtype::Mixed);
+        assert_eq!(multipart.boundary(), "outer");
+
+        let parts = multipart.parts();
+        assert_eq!(parts.len(), 2);
+        assert_eq!(parts[0].content_type(), Some(mime::TEXT_PLAIN));
+        assert_eq!(parts[0].bytes(), Some(b"hello".as_slice()));
+
+        let related = match &parts[1].body {
+            PartBody::Multipart(related) => related,
+            PartBody::Bytes(_) => panic!("expected a nested multipart body"),
+        };
+        assert_eq!(related.subtype(), &Subtype::Related);
+        assert_eq!(related.get_param("type"), Some("application/json"));
+        assert_eq!(related.parts()[0].bytes(), Some(b"{}".as_slice()));
+        assert_eq!(
+            related.parts()[0].headers.get("content-id").unwrap(),
+            "<meta>"
+        );
+        assert!(related.parts()[1].headers.is_empty());
+        assert_eq!(related.parts()[1].bytes(), Some(b"no headers".as_slice()));
+        Ok(())
+    }
+
+    #[test]
+    fn test_parse_nested_depth() -> Result<()> {
+        let nested = |depth: usize| {
+            let mut multipart =
+                Multipart::with_boundary(Subtype::Mixed, "b0".into()).part(BodyPart::new("x"));
+            for i in 1..=depth {
+                multipart = Multipart::with_boundary(Subtype::Mixed, format!("b{i}"))
+                    .part(BodyPart::nested(multipart));
+            }
+            (multipart.content_type(), multipart.build())
+        };
+
+        let (content_type, body) = nested(constants::MAX_DEPTH);
+        Multipart::parse(&content_type, &body)?;
+        let (content_type, body) = nested(constants::MAX_DEPTH + 1);
+        assert!(Multipart::parse(&content_type, &body).is_err());
+        Ok(())
+    }
+
+    #[test]
+    fn test_content_type() {
+        let multipart =
+            Multipart::with_boundary(Subtype::Related, "a b".into()).param("type", r#"text/"x"\y"#);
+        assert_eq!(
+            multipart.content_type(),
+            r#"multipart/related; boundary="a b"; type="text/\"x\"\\y""#
+        );
+        assert!(multipart.check().is_ok());
+
+        // the invalid header of a nested body is reported by the outer one
+        let invalid = Multipart::mixed().param("type", "a\nb");
+        let part = BodyPart::nested(invalid);
+        assert!(part.headers.get(CONTENT_TYPE).is_none());
+        assert!(Multipart::mixed().part(part).check().is_err());
+    }
+
+    #[test]
+    fn test_build_and_parse_byteranges() -> Result<()> {
+        let multipart = Multipart::byteranges()
+            .part(
+                BodyPart::byterange("abc", ContentRange::new(0, 2, Some(10)))
+                    .mime(mime::TEXT_PLAIN),
+            )
+            .part(
+                BodyPart::byterange("hij", ContentRange::new(7, 9, Some(10)))
+                    .mime(mime::TEXT_PLAIN),
+            );
+        let content_type = multipart.content_type();
+        let body = multipart.build();
+
+        let parsed = Multipart::parse(&content_type, &body)?;
+        assert_eq!(parsed.subtype(), &Subtype::ByteRanges);
+        let ranges = parsed
+            .parts()
+            .iter()
+            .map(|part| (part.content_range().unwrap(), part.bytes().unwrap()))
+            .collect::<Vec<_>>();
+        assert_eq!(
+            ranges,
+            [
+                (ContentRange::new(0, 2, Some(10)), b"abc".as_slice()),
+                (ContentRange::new(7, 9, Some(10)), b"hij".as_slice()),
+            ]
+        );
+        Ok(())
+    }
+
+    #[test]
+    fn test_multipart_reader() -> Result<()> {
+        // yield a single byte per read, so that boundaries are split across chunks
+        struct Trickle(std::io::Cursor<Vec<u8>>);
+
+        impl Read for Trickle {
+            fn read(&mut self, buf: &mut [u8]) -> io::Result<usize> {
+                let len = buf.len().min(1);
+                self.0.read(&mut buf[..len])
+            }
+        }
+
+        let data = b"--boundary\r\nContent-Type: text/plain\r\n\r\nfirst\r\n-boundary\r\n--boundary\r\nContent-Range: bytes 5-9/10\r\n\r\nskipped\r\n--boundary\r\n\r\n\r\n--boundary--";
+        let body = Body::Reader(Box::new(Trickle(std::io::Cursor::new(data.to_vec()))));
+        let mut reader = MultipartReader::new("multipart/mixed; boundary=boundary".parse()?, body)?;
+        assert_eq!(reader.subtype(), Subtype::Mixed);
+
+        let part = reader.next_part()?.unwrap();
+        assert_eq!(part.content_type(), Some(mime::TEXT_PLAIN));
+        assert_eq!(part.bytes()?, b"first\r\n-boundary");
+
+        // the content of this part is never read
+        let part = reader.next_part()?.unwrap();
+        assert_eq!(
+            part.content_range(),
+            Some(ContentRange::new(5, 9, Some(10)))
+        );
+
+        let part = reader.next_part()?.unwrap();
+        assert!(part.headers().is_empty());
+        assert_eq!(part.bytes()?, b"");
+
+        assert!(reader.next_part()?.is_none());
+        assert!(reader.next_part()?.is_none());
+        Ok(())
+    }
+
+    #[test]
+    fn test_multipart_reader_incomplete() -> Result<()> {
+        let body = Body::Bytes(b"--boundary\r\n\r\ntruncated".to_vec());
+        let mut reader = MultipartReader::new("multipart/mixed; boundary=boundary".parse()?, body)?;
+        let part = reader.next_part()?.unwrap();
+        assert!(part.bytes().is_err());
+        Ok(())
+    }
+
+    #[test]
+    fn test_multipart_reader_unterminated() -> Result<()> {
+        // neither the header block nor the preamble ever end
+        let header = Read::chain(&b"--boundary\r\nX-Padding: "[..], io::repeat(b'a'));
+        let body = Body::Reader(Box::new(header));
+        let mut reader = MultipartReader::new("multipart/mixed; boundary=boundary".parse()?, body)?;
+        assert!(reader.next_part().is_err());
+
+        let body = Body::Reader(Box::new(io::repeat(b'a')));
+        let mut reader = MultipartReader::new("multipart/mixed; boundary=boundary".parse()?, body)?;
+        assert!(reader.next_part().is_err());
+        Ok(())
+    }
 }
@@ -0,0 +1,196 @@
+use crate::typed_header::QualityItem;
+
+use mime::Mime;
+
+/// Pick the available value with the highest quality, the first one on ties, skipping the ones
+/// with a quality of 0.
+fn best<'a, F>(available: &[&'a str], quality: F) -> Option<&'a str>
+where
+    F: Fn(&str) -> Option<f32>,
+{
+    let mut best = None;
+    for value in available {
+        match quality(value) {
+            Some(q) if q > 0.0 && best.is_none_or(|(_, best_q)| q > best_q) => {
+                best = Some((*value, q))
+            }
+            _ => {}
+        }
+    }
+    best.map(|(value, _)| value)
+}
+
+/// Pick the media type of `available` preferred by the `Accept` media ranges, see RFC 9110
+/// section 12.5.1.
+///
+/// Each media type gets the quality of the most specific range matching it, parameters
+/// included. Without any range, the first valid media type is picked.
+pub(crate) fn media_type<'a>(
+    ranges: &[QualityItem<Mime>],
+    available: &[&'a str],
+) -> Option<&'a str> {
+    best(available, |value| {
+        let mime = value.parse::<Mime>().ok()?;
+        if ranges.is_empty() {
+            return Some(1.0);
+        }
+        ranges
+            .iter()
+            .filter(|range| media_range_matches(&range.item, &mime))
+            // on equal specificity the first range wins
+            .rev()
+            .max_by_key(|range| media_range_specificity(&range.item))
+            .map(|range| range.quality)
+    })
+}
+
+fn media_range_matches(range: &Mime, mime: &Mime) -> bool {
+    (range.type_() == mime::STAR || range.type_() == mime.type_())
+        && (range.subtype() == mime::STAR || range.subtype() == mime.subtype())
+        && range
+            .params()
+            .all(|(name, value)| mime.get_param(name) == Some(value))
+}
+
+fn media_range_specificity(range: &Mime) -> usize {
+    if range.type_() == mime::STAR {
+        0
+    } else if range.subtype() == mime::STAR {
+        1
+    } else {
+        2 + range.params().count()
+    }
+}
+
+/// Pick the language tag of `available` preferred by the `Accept-Language` ranges, using the
+/// basic filtering of RFC 4647 section 3.3.1: `en` matches both `en` and `en-US`.
+///
+/// Each tag gets the quality of the longest range matching it. Without any range, the first tag
+/// is picked.
+pub(crate) fn language<'a>(
+    ranges: &[QualityItem<String>],
+    available: &[&'a str],
+) -> Option<&'a str> {
+    best(available, |tag| {
+        if ranges.is_empty() {
+            return Some(1.0);
+        }
+        ranges
+            .iter()
+            .filter(|range| language_range_matches(&range.item, tag))
+            .rev()
+            .max_by_key(|range| {
+                if range.item == "*" {
+                    0
+                } else {
+                    range.item.len()
+                }
+            })
+            .map(|range| range.quality)
+    })
+}
+
+fn language_range_matches(range: &str, tag: &str) -> bool {
+    range == "*"
+        || tag.eq_ignore_ascii_case(range)
+        || (tag.len() > range.len()
+            && tag.as_bytes()[range.len()] == b'-'
+            && tag[..range.len()].eq_ignore_ascii_case(range))
+}
+
+/// Pick the content coding of `available` preferred by the `Accept-Encoding` codings, see RFC
+/// 9110 section 12.5.3.
+///
+/// `identity` is acceptable unless it is excluded explicitly or with `*;q=0`. Without any coding,
+/// the first one is picked.
+pub(crate) fn encoding<'a>(
+    codings: &[QualityItem<String>],
+    available: &[&'a str],
+) -> Option<&'a str> {
+    best(available, |coding| {
+        if codings.is_empty() {
+            return Some(1.0);
+        }
+        let quality = |name: &str| {
+            codings
+                .iter()
+                .find(|c| c.item.eq_ignore_ascii_case(name))
+                .map(|c| c.quality)
+        };
+        quality(coding)
+            .or_else(|| quality("*"))
+            .or_else(|| coding.eq_ignore_ascii_case("identity").then_some(1.0))
+    })
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::header::{HeaderMap, HeaderValue, ACCEPT};
+    use crate::typed_header::parse_quality_list;
+
+    fn ranges<T>(value: &'static str) -> Vec<QualityItem<T>>
+    where
+        T: std::str::FromStr,
+        <T as std::str::FromStr>::Err: Into<anyhow::Error>,
+    {
+        let mut headers = HeaderMap::new();
+        headers.insert(ACCEPT, HeaderValue::from_static(value));
+        parse_quality_list(headers.get_all(ACCEPT))
+    }
+
+    #[test]
+    fn test_media_type() {
+        let available = ["application/json", "text/html"];
+        assert_eq!(media_type(&[], &available), Some("application/json"));
+        let accept = ranges("text/html, application/xhtml+xml, */*;q=0.8");
+        assert_eq!(media_type(&accept, &available), Some("text/html"));
+        let accept = ranges("text/*;q=0.5, application/json;q=0.4");
+        assert_eq!(media_type(&accept, &available), Some("text/html"));
+        // the more specific range wins, even with a lower quality
+        let accept = ranges("*/*, text/html;q=0");
+        assert_eq!(media_type(&accept, &["text/html"]), None);
+        assert_eq!(media_type(&accept, &available), Some("application/json"));
+        // example of RFC 9110 section 12.5.1
+        let accept = ranges(
+            "text/*;q=0.3, text/plain;q=0.7, text/plain;format=flowed, text/plain;format=fixed;q=0.4, */*;q=0.5",
+        );
+        assert_eq!(
+            media_type(&accept, &["text/plain;format=flowed", "text/plain"]),
+            Some("text/plain;format=flowed")
+        );
+        assert_eq!(
+            media_type(&accept, &["text/html", "text/plain;format=fixed"]),
+            Some("text/plain;format=fixed")
+        );
+        assert_eq!(
+            media_type(&accept, &["image/jpeg", "text/plain"]),
+            Some("text/plain")
+        );
+        assert_eq!(media_type(&ranges("image/*"), &available), None);
+    }
+
+    #[test]
+    fn test_language() {
+        let available = ["en-US", "fr", "de"];
+        assert_eq!(language(&[], &available), Some("en-US"));
+        assert_eq!(
+            language(&ranges("fr-CH, fr;q=0.9, en;q=0.8"), &available),
+            Some("fr")
+        );
+        assert_eq!(language(&ranges("EN, *;q=0.5"), &available), Some("en-US"));
+        assert_eq!(language(&ranges("*;q=0.5, de"), &available), Some("de"));
+        assert_eq!(language(&ranges("e"), &available), None);
+    }
+
+    #[test]
+    fn test_encoding() {
+        let available = ["br", "gzip", "identity"];
+        assert_eq!(encoding(&[], &available), Some("br"));
+        assert_eq!(encoding(&ranges("gzip, deflate"), &available), Some("gzip"));
+        assert_eq!(encoding(&ranges("deflate"), &available), Some("identity"));
+        assert_eq!(encoding(&ranges("br;q=0.5, *"), &available), Some("gzip"));
+        assert_eq!(encoding(&ranges("deflate, *;q=0"), &available), None);
+        assert_eq!(encoding(&ranges("identity;q=0"), &["identity"]), None);
+    }
+}
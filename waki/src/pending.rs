@@ -0,0 +1,186 @@
+use crate::{
+    bindings::wasi::{
+        clocks::monotonic_clock,
+        http::types::FutureIncomingResponse,
+        io::poll::{poll, Pollable},
+    },
+    RequestBuilder, Response,
+};
+
+use anyhow::{anyhow, Error, Result};
+use std::collections::VecDeque;
+
+/// A request in flight, returned by [`RequestBuilder::start`].
+///
+/// Dropping it cancels the request.
+pub struct PendingResponse {
+    // pollable resource is a child: it must be dropped before the parent future-incoming-response is dropped
+    pollable: Pollable,
+    future_response: FutureIncomingResponse,
+    // monotonic clock instant after which the request times out
+    deadline: Option<u64>,
+}
+
+impl PendingResponse {
+    pub(crate) fn new(future_response: FutureIncomingResponse, timeout: Option<u64>) -> Self {
+        Self {
+            pollable: future_response.subscribe(),
+            future_response,
+            deadline: timeout.map(|timeout| monotonic_clock::now().saturating_add(timeout)),
+        }
+    }
+
+    /// Check whether the response head has been received, or the request has failed, without
+    /// blocking.
+    #[inline]
+    pub fn is_ready(&self) -> bool {
+        self.pollable.ready()
+    }
+
+    /// Wait for the response, blocking until its head is received or the timeout expires.
+    pub fn wait(self) -> Result<Response> {
+        match self.deadline {
+            Some(deadline) => {
+                let timer = monotonic_clock::subscribe_instant(deadline);
+                poll(&[&self.pollable, &timer]);
+            }
+            None => self.pollable.block(),
+        }
+        self.take()
+    }
+
+    /// Wait for the response without blocking other tasks of the executor in [`crate::rt`].
+    ///
+    /// # Optional
+    ///
+    /// This requires the `async` feature enabled.
+    #[cfg(feature = "async")]
+    pub async fn wait_async(self) -> Result<Response> {
+        let mut pollables = vec![self.future_response.subscribe()];
+        if let Some(deadline) = self.deadline {
+            pollables.push(monotonic_clock::subscribe_instant(deadline));
+        }
+        crate::rt::wait_any(pollables).await;
+        self.take()
+    }
+
+    /// Take the response if it is ready, otherwise the request has timed out.
+    fn take(self) -> Result<Response> {
+        let Self {
+            pollable,
+            future_response,
+            ..
+        } = self;
+        drop(pollable);
+
+        let incoming_response = match future_response.get() {
+            Some(result) => result.map_err(|()| anyhow!("response already taken"))?,
+            None => return Err(anyhow!("request timed out")),
+        }?;
+        drop(future_response);
+
+        incoming_response.try_into()
+    }
+}
+
+/// An iterator over the responses of several requests in flight, yielding each one together
+/// with its position as soon as it completes.
+///
+/// All the requests are polled together with `wasi:io/poll`, so the total time is the one of
+/// the slowest request instead of the sum of all of them.
+///
+/// ```
+/// # use anyhow::Result;
+/// # use waki::Client;
+/// # fn run() -> Result<()> {
+/// let client = Client::new();
+/// let urls = ["https://httpbin.org/get", "https://httpbin.org/ip"];
+/// for (index, resp) in client.send_all(urls.iter().map(|url| client.get(url))) {
+///     println!("{}: {}", urls[index], resp?.status_code());
+/// }
+/// # Ok(())
+/// # }
+/// ```
+pub struct Join {
+    pending: Vec<(usize, PendingResponse)>,
+    // requests which failed before being sent
+    failed: VecDeque<(usize, Error)>,
+}
+
+impl Join {
+    pub fn new<I: IntoIterator<Item = PendingResponse>>(pending: I) -> Self {
+        Self {
+            pending: pending.into_iter().enumerate().collect(),
+            failed: VecDeque::new(),
+        }
+    }
+
+    pub(crate) fn start<I: IntoIterator<Item = RequestBuilder>>(requests: I) -> Self {
+        let mut join = Self::new([]);
+        for (index, request) in requests.into_iter().enumerate() {
+            match request.start() {
+                Ok(pending) => join.pending.push((index, pending)),
+                Err(e) => join.failed.push_back((index, e)),
+            }
+        }
+        join
+    }
+
+    /// The number of responses not yielded yet.
+    #[inline]
+    pub fn len(&self) -> usize {
+        self.pending.len() + self.failed.len()
+    }
+
+    #[inline]
+    pub fn is_empty(&self) -> bool {
+        self.len() == 0
+    }
+}
+
+impl Iterator for Join {
+    type Item = (usize, Result<Response>);
+
+    fn next(&mut self) -> Option<Self::Item> {
+        if let Some((index, e)) = self.failed.pop_front() {
+            return Some((index, Err(e)));
+        }
+        if self.pending.is_empty() {
+            return None;
+        }
+
+        let timers = self
+            .pending
+            .iter()
+            .enumerate()
+            .filter_map(|(pos, (_, pending))| {
+                pending
+                    .deadline
+                    .map(|deadline| (pos, monotonic_clock::subscribe_instant(deadline)))
+            })
+            .collect::<Vec<_>>();
+        let pollables = self
+            .pending
+            .iter()
+            .map(|(_, pending)| &pending.pollable)
+            .chain(timers.iter().map(|(_, timer)| timer))
+            .collect::<Vec<_>>();
+
+        let ready = poll(&pollables)[0] as usize;
+        let pos = match ready.checked_sub(self.pending.len()) {
+            Some(timer) => timers[timer].0,
+            None => ready,
+        };
+        drop(pollables);
+        drop(timers);
+
+        let (index, pending) = self.pending.swap_remove(pos);
+        Some((index, pending.take()))
+    }
+
+    fn size_hint(&self) -> (usize, Option<usize>) {
+        (self.len(), Some(self.len()))
+    }
+}
+
+impl ExactSizeIterator for Join {}
@@ -0,0 +1,169 @@
+use crate::{
+    header::{
+        HeaderMap, HeaderName, HeaderValue, CONNECTION, FORWARDED, HOST, PROXY_AUTHENTICATE,
+        PROXY_AUTHORIZATION, TE, TRAILER, TRANSFER_ENCODING, UPGRADE,
+    },
+    Request,
+};
+
+use anyhow::{anyhow, Result};
+use http::{
+    uri::{Authority, Parts, PathAndQuery},
+    Uri,
+};
+
+/// Headers that only apply to a single connection, see RFC 9110 section 7.6.1.
+const HOP_BY_HOP_HEADERS: [HeaderName; 9] = [
+    CONNECTION,
+    HeaderName::from_static("keep-alive"),
+    HeaderName::from_static("proxy-connection"),
+    PROXY_AUTHENTICATE,
+    PROXY_AUTHORIZATION,
+    TE,
+    TRAILER,
+    TRANSFER_ENCODING,
+    UPGRADE,
+];
+
+const X_FORWARDED_HOST: HeaderName = HeaderName::from_static("x-forwarded-host");
+const X_FORWARDED_PROTO: HeaderName = HeaderName::from_static("x-forwarded-proto");
+
+/// Remove the hop-by-hop headers, including the ones listed in the `Connection` header.
+pub(crate) fn strip_hop_by_hop_headers(headers: &mut HeaderMap) {
+    let listed = headers
+        .get_all(CONNECTION)
+        .iter()
+        .filter_map(|v| v.to_str().ok())
+        .flat_map(|v| v.split(','))
+        .filter_map(|name| HeaderName::try_from(name.trim()).ok())
+        .collect::<Vec<_>>();
+    for name in listed.iter().chain(HOP_BY_HOP_HEADERS.iter()) {
+        headers.remove(name);
+    }
+}
+
+/// Turn an incoming request into a request to the upstream `url`.
+///
+/// The scheme and authority are taken from `url`, its path is prepended to the path of the
+/// incoming request and the query string is kept. The body is moved as is, so it is streamed
+/// to the upstream without being buffered.
+pub(crate) fn forward_request(req: Request, url: &str) -> Result<Request> {
+    let upstream = url.parse::<Uri>()?.into_parts();
+    if upstream.authority.is_none() {
+        return Err(anyhow!("the upstream URL must be absolute: {url}"));
+    }
+
+    let mut headers = req.headers;
+    strip_hop_by_hop_headers(&mut headers);
+    // the authority of the outgoing request is the one of the upstream
+    // only a valid authority is copied, it could otherwise end the quoted string of `Forwarded`
+    let host = headers
+        .remove(HOST)
+        .and_then(|v| v.to_str().ok()?.parse::<Authority>().ok())
+        .or_else(|| req.uri.authority.clone())
+        .map(|a| a.to_string());
+    let proto = req.uri.scheme.as_ref().map(|s| s.to_string());
+
+    let mut element = vec![];
+    if let Some(host) = &host {
+        element.push(format!("host=\"{host}\""));
+        if !headers.contains_key(X_FORWARDED_HOST) {
+            headers.insert(X_FORWARDED_HOST, HeaderValue::try_from(host.as_str())?);
+        }
+    }
+    if let Some(proto) = &proto {
+        element.push(format!("proto={proto}"));
+        if !headers.contains_key(X_FORWARDED_PROTO) {
+            headers.insert(X_FORWARDED_PROTO, HeaderValue::try_from(proto.as_str())?);
+        }
+    }
+    if !element.is_empty() {
+        // the previous proxies may have sent the header several times, fold them in one list
+        let mut forwarded = headers
+            .get_all(FORWARDED)
+            .iter()
+            .filter_map(|v| v.to_str().ok())
+            .collect::<Vec<_>>()
+            .join(", ");
+        if !forwarded.is_empty() {
+            forwarded.push_str(", ");
+        }
+        forwarded.push_str(&element.join(";"));
+        headers.insert(FORWARDED, HeaderValue::try_from(forwarded)?);
+    }
+
+    let prefix = upstream
+        .path_and_query
+        .as_ref()
+        .map(|p| p.path().trim_end_matches('/'))
+        .unwrap_or_default();
+    let path_and_query = match &req.uri.path_and_query {
+        Some(p) => format!("{prefix}{}", p.as_str()),
+        None => format!("{prefix}/"),
+    };
+
+    let mut uri = Parts::default();
+    uri.scheme = upstream.scheme;
+    uri.authority = upstream.authority;
+    uri.path_and_query = Some(PathAndQuery::try_from(path_and_query)?);
+
+    let mut forwarded = Request::new(req.method, uri);
+    forwarded.headers = headers;
+    forwarded.body = req.body;
+    Ok(forwarded)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::Method;
+
+    #[test]
+    fn test_forward_request() -> Result<()> {
+        let mut uri = Parts::default();
+        uri.scheme = Some("https".parse()?);
+        uri.authority = Some("gateway.example.com".parse()?);
+        uri.path_and_query = Some("/users?page=2".parse()?);
+        let mut req = Request::new(Method::Post, uri);
+        req.headers
+            .insert(CONNECTION, "keep-alive, x-internal".parse()?);
+        req.headers.insert("keep-alive", "timeout=5".parse()?);
+        req.headers.insert("x-internal", "secret".parse()?);
+        req.headers.insert(HOST, "gateway.example.com".parse()?);
+        req.headers.insert(FORWARDED, "for=192.0.2.60".parse()?);
+        req.headers.append(FORWARDED, "for=198.51.100.17".parse()?);
+        req.headers.insert("x-request-id", "42".parse()?);
+
+        let forwarded = forward_request(req, "http://backend:8080/api/")?;
+        assert_eq!(forwarded.uri.scheme.unwrap().as_str(), "http");
+        assert_eq!(forwarded.uri.authority.unwrap().as_str(), "backend:8080");
+        assert_eq!(
+            forwarded.uri.path_and_query.unwrap().as_str(),
+            "/api/users?page=2"
+        );
+
+        let headers = forwarded.headers;
+        for name in [CONNECTION.as_str(), "keep-alive", "x-internal", "host"] {
+            assert!(!headers.contains_key(name), "{name} should be stripped");
+        }
+        assert_eq!(headers["x-request-id"], "42");
+        assert_eq!(headers[X_FORWARDED_HOST], "gateway.example.com");
+        assert_eq!(headers[X_FORWARDED_PROTO], "https");
+        assert_eq!(
+            headers[FORWARDED],
+            "for=192.0.2.60, for=198.51.100.17, host=\"gateway.example.com\";proto=https"
+        );
+        Ok(())
+    }
+
+    #[test]
+    fn test_forward_request_invalid_host() -> Result<()> {
+        let mut req = Request::new(Method::Get, Parts::default());
+        req.headers.insert(HOST, r#"a";for=10.0.0.1"#.parse()?);
+
+        let forwarded = forward_request(req, "http://backend:8080")?;
+        assert!(!forwarded.headers.contains_key(FORWARDED));
+        assert!(!forwarded.headers.contains_key(X_FORWARDED_HOST));
+        Ok(())
+    }
+}
@@ -0,0 +1,473 @@
+use crate::{
+    body::Body,
+    header::{ACCEPT_RANGES, CONTENT_RANGE, CONTENT_TYPE, ETAG, IF_RANGE, LAST_MODIFIED, RANGE},
+    EntityTag, ErrorCode, Method, Request, Response,
+};
+
+use anyhow::{anyhow, Error, Result};
+use mime::Mime;
+use std::collections::{hash_map::RandomState, VecDeque};
+use std::fmt;
+use std::hash::{BuildHasher, Hasher};
+use std::io::{self, Cursor, Read, Seek, SeekFrom};
+use std::str::FromStr;
+use std::time::{Duration, SystemTime, UNIX_EPOCH};
+
+/// The value of a `Content-Range` header, e.g. `bytes 0-499/1234`.
+///
+/// `end` is inclusive, matching the wire format.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct ContentRange {
+    pub start: u64,
+    pub end: u64,
+    /// The complete length of the representation, `None` if unknown (`*`).
+    pub complete_length: Option<u64>,
+}
+
+impl ContentRange {
+    #[inline]
+    pub fn new(start: u64, end: u64, complete_length: Option<u64>) -> Self {
+        Self {
+            start,
+            end,
+            complete_length,
+        }
+    }
+}
+
+impl fmt::Display for ContentRange {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        write!(f, "bytes {}-{}/", self.start, self.end)?;
+        match self.complete_length {
+            Some(len) => write!(f, "{len}"),
+            None => f.write_str("*"),
+        }
+    }
+}
+
+impl FromStr for ContentRange {
+    type Err = Error;
+
+    fn from_str(s: &str) -> Result<Self> {
+        let spec = s
+            .trim()
+            .strip_prefix("bytes ")
+            .ok_or_else(|| anyhow!("unsupported Content-Range unit: {s}"))?;
+        let (range, complete_length) = spec
+            .split_once('/')
+            .ok_or_else(|| anyhow!("invalid Content-Range: {s}"))?;
+        let (start, end) = range
+            .split_once('-')
+            .ok_or_else(|| anyhow!("invalid Content-Range: {s}"))?;
+        let (start, end) = (start.trim().parse::<u64>()?, end.trim().parse::<u64>()?);
+        let complete_length = match complete_length.trim() {
+            "*" => None,
+            len => Some(len.parse::<u64>()?),
+        };
+        if end < start || complete_length.is_some_and(|len| end >= len) {
+            return Err(anyhow!("invalid Content-Range: {s}"));
+        }
+        Ok(Self::new(start, end, complete_length))
+    }
+}
+
+/// A range of a `Range` header, see RFC 9110 section 14.1.2.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub(crate) enum ByteRange {
+    /// `first-last`, both inclusive.
+    FromTo(u64, u64),
+    /// `first-`, up to the end.
+    From(u64),
+    /// `-length`, the last `length` bytes.
+    Last(u64),
+}
+
+impl ByteRange {
+    /// Resolve the range against a representation of `len` bytes, returning the inclusive first
+    /// and last positions, `None` if it is not satisfiable.
+    pub(crate) fn resolve(&self, len: u64) -> Option<(u64, u64)> {
+        match *self {
+            Self::FromTo(first, last) if first < len => Some((first, last.min(len - 1))),
+            Self::From(first) if first < len => Some((first, len - 1)),
+            Self::Last(length) if length > 0 && len > 0 => {
+                Some((len.saturating_sub(length), len - 1))
+            }
+            _ => None,
+        }
+    }
+}
+
+impl fmt::Display for ByteRange {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            Self::FromTo(first, last) => write!(f, "{first}-{last}"),
+            Self::From(first) => write!(f, "{first}-"),
+            Self::Last(length) => write!(f, "-{length}"),
+        }
+    }
+}
+
+/// The maximum number of ranges of a `Range` header, more are ignored to bound the work of
+/// answering a single request, as allowed by RFC 9110 section 14.2.
+const MAX_RANGES: usize = 50;
+
+/// Parse the ranges of a `Range` header, `None` if the unit is not `bytes`, a range is invalid or
+/// there are more than [`MAX_RANGES`], in which case the header must be ignored.
+pub(crate) fn parse_ranges(value: &str) -> Option<Vec<ByteRange>> {
+    let (unit, ranges) = value.trim().split_once('=')?;
+    if !unit.trim().eq_ignore_ascii_case("bytes") || ranges.split(',').nth(MAX_RANGES).is_some() {
+        return None;
+    }
+    ranges
+        .split(',')
+        .map(str::trim)
+        .filter(|range| !range.is_empty())
+        .map(|range| {
+            let (first, last) = range.split_once('-')?;
+            let (first, last) = (first.trim(), last.trim());
+            match (first.is_empty(), last.is_empty()) {
+                (true, false) => Some(ByteRange::Last(last.parse().ok()?)),
+                (false, true) => Some(ByteRange::From(first.parse().ok()?)),
+                (false, false) => {
+                    let (first, last) = (first.parse().ok()?, last.parse().ok()?);
+                    (first <= last).then_some(ByteRange::FromTo(first, last))
+                }
+                (true, true) => None,
+            }
+        })
+        .collect::<Option<Vec<_>>>()
+        .filter(|ranges| !ranges.is_empty())
+}
+
+/// Answer a request with the parts of a seekable body selected by its `Range` header, see
+/// RFC 9110 section 14.
+///
+/// Without a usable `Range` header, which is only honored for `GET` requests and up to 50 ranges,
+/// or when the `If-Range` validator does not match the [`etag`](Self::etag) or
+/// [`last_modified`](Self::last_modified) set here, the whole body is sent. A single range is
+/// answered with `206 Partial Content` and a `Content-Range` header, several ones with a
+/// `multipart/byteranges` body, and `416 Range Not Satisfiable` is returned when none of them
+/// overlaps the body. Only the requested bytes are read from the source.
+///
+/// ```
+/// use std::io::Cursor;
+/// use waki::{handler, ErrorCode, Ranged, Request, Response};
+///
+/// #[handler]
+/// fn hello(req: Request) -> Result<Response, ErrorCode> {
+///     let data = b"Hello, WASI!".to_vec();
+///     let len = data.len() as u64;
+///     Ranged::new(Cursor::new(data), len).respond(&req)
+/// }
+/// ```
+pub struct Ranged<R> {
+    source: R,
+    len: u64,
+    content_type: Option<Mime>,
+    etag: Option<EntityTag>,
+    last_modified: Option<SystemTime>,
+}
+
+impl<R: Read + Seek + Send + 'static> Ranged<R> {
+    /// Serve `len` bytes of `source`, starting at its beginning.
+    pub fn new(source: R, len: u64) -> Self {
+        Self {
+            source,
+            len,
+            content_type: None,
+            etag: None,
+            last_modified: None,
+        }
+    }
+
+    /// Set the `Content-Type` of the body, also used for the parts of a `multipart/byteranges`
+    /// body.
+    pub fn content_type(mut self, mime: Mime) -> Self {
+        self.content_type = Some(mime);
+        self
+    }
+
+    /// Set the `ETag` of the body, matched against `If-Range`.
+    pub fn etag(mut self, etag: EntityTag) -> Self {
+        self.etag = Some(etag);
+        self
+    }
+
+    /// Set the `Last-Modified` date of the body, matched against `If-Range`.
+    pub fn last_modified(mut self, last_modified: SystemTime) -> Self {
+        self.last_modified = Some(truncate_to_secs(last_modified));
+        self
+    }
+
+    /// Build the response to the request.
+    ///
+    /// The body of the response to a `HEAD` request is left empty.
+    pub fn respond(self, req: &Request) -> Result<Response, ErrorCode> {
+        self.try_respond(req)
+            .unwrap_or_else(|e| Err(ErrorCode::InternalError(Some(e.to_string()))))
+    }
+
+    fn try_respond(mut self, req: &Request) -> io::Result<Result<Response, ErrorCode>> {
+        let len = self.len;
+        let mut builder = Response::builder().header(ACCEPT_RANGES, "bytes");
+        if let Some(etag) = &self.etag {
+            builder = builder.header(ETAG, etag.to_string());
+        }
+        if let Some(last_modified) = self.last_modified {
+            builder = builder.header(LAST_MODIFIED, httpdate::fmt_http_date(last_modified));
+        }
+
+        let ranges = match req.header(RANGE).and_then(|v| v.to_str().ok()) {
+            // range handling is only defined for GET, see RFC 9110 section 14.2
+            Some(value) if matches!(req.method, Method::Get) && self.if_range_matches(req) => {
+                parse_ranges(value)
+            }
+            _ => None,
+        };
+        let (length, body): (u64, Box<dyn Read + Send>) = match ranges {
+            None => {
+                if let Some(content_type) = self.content_type.take() {
+                    builder = builder.content_type(content_type);
+                }
+                self.source.seek(SeekFrom::Start(0))?;
+                (len, Box::new(self.source.take(len)))
+            }
+            Some(ranges) => match coalesce(&ranges, len).as_slice() {
+                [] => {
+                    return Ok(builder
+                        .status_code(416)
+                        .header(CONTENT_RANGE, format!("bytes */{len}"))
+                        .build());
+                }
+                &[(first, last)] => {
+                    builder = builder.status_code(206).header(
+                        CONTENT_RANGE,
+                        ContentRange::new(first, last, Some(len)).to_string(),
+                    );
+                    if let Some(content_type) = self.content_type.take() {
+                        builder = builder.content_type(content_type);
+                    }
+                    self.source.seek(SeekFrom::Start(first))?;
+                    (
+                        last - first + 1,
+                        Box::new(self.source.take(last - first + 1)),
+                    )
+                }
+                ranges => {
+                    let reader =
+                        ByteRangesReader::new(self.source, ranges, len, self.content_type.as_ref());
+                    builder = builder.status_code(206).header(
+                        CONTENT_TYPE,
+                        format!("multipart/byteranges; boundary={}", reader.boundary),
+                    );
+                    (reader.content_length(), Box::new(reader))
+                }
+            },
+        };
+
+        // the body of a HEAD response is empty, so its Content-Length could not be honored
+        if matches!(req.method, Method::Head) {
+            return Ok(builder.build());
+        }
+        Ok(builder.content_length(length).build().map(|mut resp| {
+            resp.body = Body::Reader(body);
+            resp
+        }))
+    }
+
+    /// Check whether the representation is unchanged according to `If-Range`, see RFC 9110
+    /// section 13.1.5: the range is ignored otherwise.
+    fn if_range_matches(&self, req: &Request) -> bool {
+        let Some(value) = req.header(IF_RANGE).and_then(|v| v.to_str().ok()) else {
+            return true;
+        };
+        match value.parse::<EntityTag>() {
+            Ok(tag) => self.etag.as_ref().is_some_and(|etag| etag.strong_eq(&tag)),
+            Err(_) => match (httpdate::parse_http_date(value), self.last_modified) {
+                (Ok(date), Some(last_modified)) => date == last_modified,
+                _ => false,
+            },
+        }
+    }
+}
+
+/// Resolve the satisfiable ranges against a body of `len` bytes, merging the ones that overlap
+/// or are adjacent, as allowed by RFC 9110 section 14.1.1.
+fn coalesce(ranges: &[ByteRange], len: u64) -> Vec<(u64, u64)> {
+    let mut resolved: Vec<_> = ranges.iter().filter_map(|r| r.resolve(len)).collect();
+    resolved.sort_unstable();
+    let mut merged: Vec<(u64, u64)> = Vec::with_capacity(resolved.len());
+    for (first, last) in resolved {
+        match merged.last_mut() {
+            Some(prev) if first <= prev.1.saturating_add(1) => prev.1 = prev.1.max(last),
+            _ => merged.push((first, last)),
+        }
+    }
+    merged
+}
+
+/// HTTP dates have a precision of one second.
+fn truncate_to_secs(time: SystemTime) -> SystemTime {
+    match time.duration_since(UNIX_EPOCH) {
+        Ok(d) => UNIX_EPOCH + Duration::from_secs(d.as_secs()),
+        Err(_) => time,
+    }
+}
+
+/// Stream a `multipart/byteranges` body, see RFC 9110 section 14.6, seeking the source to each
+/// range in turn.
+struct ByteRangesReader<R> {
+    source: R,
+    boundary: String,
+    headers: Vec<Vec<u8>>,
+    ranges: VecDeque<(u64, u64)>,
+    pending: Cursor<Vec<u8>>,
+    remaining: u64,
+    finished: bool,
+}
+
+impl<R: Read + Seek> ByteRangesReader<R> {
+    fn new(source: R, ranges: &[(u64, u64)], len: u64, content_type: Option<&Mime>) -> Self {
+        let boundary = format!("{:016x}", RandomState::new().build_hasher().finish());
+        let headers = ranges
+            .iter()
+            .enumerate()
+            .map(|(i, &(first, last))| {
+                let mut header = String::new();
+                if i > 0 {
+                    header.push_str("\r\n");
+                }
+                header.push_str(&format!("--{boundary}\r\n"));
+                if let Some(content_type) = content_type {
+                    header.push_str(&format!("Content-Type: {content_type}\r\n"));
+                }
+                header.push_str(&format!(
+                    "Content-Range: {}\r\n\r\n",
+                    ContentRange::new(first, last, Some(len))
+                ));
+                header.into_bytes()
+            })
+            .collect();
+        Self {
+            source,
+            boundary,
+            headers,
+            ranges: ranges.iter().copied().collect(),
+            pending: Cursor::new(vec![]),
+            remaining: 0,
+            finished: false,
+        }
+    }
+
+    fn closing(&self) -> Vec<u8> {
+        format!("\r\n--{}--\r\n", self.boundary).into_bytes()
+    }
+
+    fn content_length(&self) -> u64 {
+        let headers: usize = self.headers.iter().map(Vec::len).sum();
+        let data: u64 = self
+            .ranges
+            .iter()
+            .map(|(first, last)| last - first + 1)
+            .sum();
+        headers as u64 + data + self.closing().len() as u64
+    }
+}
+
+impl<R: Read + Seek> Read for ByteRangesReader<R> {
+    fn read(&mut self, buf: &mut [u8]) -> io::Result<usize> {
+        loop {
+            let n = self.pending.read(buf)?;
+            if n > 0 {
+                return Ok(n);
+            }
+            if self.remaining > 0 {
+                let max = buf
+                    .len()
+                    .min(usize::try_from(self.remaining).unwrap_or(usize::MAX));
+                let n = self.source.read(&mut buf[..max])?;
+                if n == 0 {
+                    return Err(io::ErrorKind::UnexpectedEof.into());
+                }
+                self.remaining -= n as u64;
+                return Ok(n);
+            }
+            match self.ranges.pop_front() {
+                Some((first, last)) => {
+                    self.source.seek(SeekFrom::Start(first))?;
+                    self.remaining = last - first + 1;
+                    let index = self.headers.len() - self.ranges.len() - 1;
+                    self.pending = Cursor::new(std::mem::take(&mut self.headers[index]));
+                }
+                None if !self.finished => {
+                    self.finished = true;
+                    self.pending = Cursor::new(self.closing());
+                }
+                None => return Ok(0),
+            }
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_parse_ranges() {
+        assert_eq!(
+            parse_ranges("bytes=0-499, 500-, -200"),
+            Some(vec![
+                ByteRange::FromTo(0, 499),
+                ByteRange::From(500),
+                ByteRange::Last(200),
+            ])
+        );
+        assert_eq!(parse_ranges("items=0-1"), None);
+        assert_eq!(parse_ranges("bytes=5-1"), None);
+        assert_eq!(parse_ranges("bytes=-"), None);
+        assert_eq!(parse_ranges("bytes="), None);
+        let ranges = vec!["0-0"; MAX_RANGES].join(",");
+        assert_eq!(
+            parse_ranges(&format!("bytes={ranges}")).map(|r| r.len()),
+            Some(MAX_RANGES)
+        );
+        assert_eq!(parse_ranges(&format!("bytes={ranges},1-1")), None);
+
+        assert_eq!(ByteRange::FromTo(0, 499).resolve(100), Some((0, 99)));
+        assert_eq!(ByteRange::From(100).resolve(100), None);
+        assert_eq!(ByteRange::Last(200).resolve(100), Some((0, 99)));
+        assert_eq!(ByteRange::Last(0).resolve(100), None);
+
+        assert_eq!(ByteRange::FromTo(0, 499).to_string(), "0-499");
+        assert_eq!(ByteRange::From(500).to_string(), "500-");
+        assert_eq!(ByteRange::Last(200).to_string(), "-200");
+    }
+
+    #[test]
+    fn test_coalesce() {
+        let ranges = parse_ranges("bytes=50-59, 0-9, 5-19, 20-29, -5, 200-").unwrap();
+        assert_eq!(coalesce(&ranges, 100), vec![(0, 29), (50, 59), (95, 99)]);
+        assert_eq!(coalesce(&ranges[5..], 100), vec![]);
+    }
+
+    #[test]
+    fn test_byte_ranges_reader() {
+        let source = Cursor::new(b"0123456789".to_vec());
+        let mut reader =
+            ByteRangesReader::new(source, &[(1, 2), (7, 9)], 10, Some(&mime::TEXT_PLAIN));
+        let boundary = reader.boundary.clone();
+        let length = reader.content_length();
+        let mut body = String::new();
+        reader.read_to_string(&mut body).unwrap();
+        assert_eq!(
+            body,
+            format!(
+                "--{boundary}\r\nContent-Type: text/plain\r\nContent-Range: bytes 1-2/10\r\n\r\n12\r\n\
+                 --{boundary}\r\nContent-Type: text/plain\r\nContent-Range: bytes 7-9/10\r\n\r\n789\r\n\
+                 --{boundary}--\r\n"
+            )
+        );
+        assert_eq!(body.len() as u64, length);
+    }
+}
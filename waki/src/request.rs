@@ -1,13 +1,32 @@
 use crate::{
+    auth::TokenProvider,
     bindings::wasi::http::{
         outgoing_handler,
-        types::{IncomingRequest, OutgoingBody, OutgoingRequest, RequestOptions},
+        types::{
+            FutureIncomingResponse, IncomingRequest, OutgoingBody, OutgoingRequest, RequestOptions,
+        },
     },
-    body::{stream_to_outgoing_body, write_to_outgoing_body, Body},
-    header::HeaderMap,
-    ErrorCode, Method, Response,
+    body::{body_to_outgoing_body, Body, Incoming, IncomingBodyStream},
+    conditional::{self, Preconditions},
+    header::{HeaderMap, ACCEPT_ENCODING, ACCEPT_LANGUAGE, AUTHORIZATION, RANGE},
+    negotiate,
+    range::ByteRange,
+    typed_header::parse_quality_list,
+    Authorization, Client, EntityTag, ErrorCode, Method, PendingResponse, Response,
 };
 
+#[cfg(all(feature = "digest-auth", feature = "async"))]
+use crate::body::write_to_outgoing_body_async;
+#[cfg(feature = "cache")]
+use crate::cache::{self, CacheStore, Lookup};
+#[cfg(feature = "signing")]
+use crate::sign::{SignableRequest, Signer, WebhookVerifier};
+#[cfg(feature = "digest-auth")]
+use crate::{auth::DigestChallenge, body::write_to_outgoing_body};
+
+#[cfg(feature = "async")]
+use crate::body::body_to_outgoing_body_async;
+
 use anyhow::{anyhow, Error, Result};
 use http::{
     uri::{Authority, Parts, PathAndQuery},
@@ -15,7 +34,9 @@ use http::{
 };
 use std::borrow::Borrow;
 use std::collections::HashMap;
-use std::time::Duration;
+use std::ops::{Bound, RangeBounds};
+use std::sync::Arc;
+use std::time::{Duration, SystemTime};
 
 pub struct RequestBuilder {
     // all errors generated while building the request will be deferred and returned when `send` the request.
@@ -95,6 +116,164 @@ impl RequestBuilder {
         self
     }
 
+    /// Set the timeout for receiving the response head, starting when the request is sent.
+    ///
+    /// ```
+    /// # use anyhow::Result;
+    /// # use std::time::Duration;
+    /// # use waki::Client;
+    /// # fn run() -> Result<()> {
+    /// let resp = Client::new().get("https://httpbin.org/delay/1")
+    ///     .timeout(Duration::from_secs(5))
+    ///     .send()?;
+    /// # Ok(())
+    /// # }
+    /// ```
+    #[inline]
+    pub fn timeout(mut self, timeout: Duration) -> Self {
+        if let Ok(ref mut req) = self.inner {
+            req.timeout = Some(timeout.as_nanos() as u64);
+        }
+        self
+    }
+
+    /// Set the `Authorization` header with Basic credentials.
+    ///
+    /// ```
+    /// # use anyhow::Result;
+    /// # use waki::Client;
+    /// # fn run() -> Result<()> {
+    /// let resp = Client::new().get("https://httpbin.org/basic-auth/user/passwd")
+    ///     .basic_auth("user", "passwd")
+    ///     .send()?;
+    /// # Ok(())
+    /// # }
+    /// ```
+    #[inline]
+    pub fn basic_auth<U: Into<String>, P: Into<String>>(self, username: U, password: P) -> Self {
+        self.authorization(Authorization::basic(username, password))
+    }
+
+    /// Only request the bytes of `range`, setting the `Range` header.
+    ///
+    /// The server answers `206 Partial Content` with a `Content-Range` header when it supports
+    /// ranges, see [`ContentRange`](crate::ContentRange), and `200 OK` with the whole body
+    /// otherwise.
+    ///
+    /// ```
+    /// # use anyhow::Result;
+    /// # use waki::Client;
+    /// # fn run() -> Result<()> {
+    /// let resp = Client::new().get("https://httpbin.org/range/1024")
+    ///     .range(100..200)
+    ///     .send()?;
+    /// # Ok(())
+    /// # }
+    /// ```
+    pub fn range<R: RangeBounds<u64>>(mut self, range: R) -> Self {
+        let first = match range.start_bound() {
+            Bound::Included(&first) => Some(first),
+            Bound::Excluded(&first) => first.checked_add(1),
+            Bound::Unbounded => Some(0),
+        };
+        let last = match range.end_bound() {
+            Bound::Included(&last) => Some(Some(last)),
+            Bound::Excluded(&end) => end.checked_sub(1).map(Some),
+            Bound::Unbounded => Some(None),
+        };
+        let range = match (first, last) {
+            (Some(first), Some(None)) => Some(ByteRange::From(first)),
+            (Some(first), Some(Some(last))) if first <= last => {
+                Some(ByteRange::FromTo(first, last))
+            }
+            _ => None,
+        };
+        match range {
+            Some(range) => self.header(RANGE, format!("bytes={range}")),
+            None => {
+                self.inner = Err(anyhow!("empty range"));
+                self
+            }
+        }
+    }
+
+    /// Set the `Authorization` header with a Bearer token.
+    ///
+    /// ```
+    /// # use anyhow::Result;
+    /// # use waki::Client;
+    /// # fn run() -> Result<()> {
+    /// let resp = Client::new().get("https://httpbin.org/bearer")
+    ///     .bearer_auth("token")
+    ///     .send()?;
+    /// # Ok(())
+    /// # }
+    /// ```
+    #[inline]
+    pub fn bearer_auth<T: Into<String>>(self, token: T) -> Self {
+        self.authorization(Authorization::bearer(token))
+    }
+
+    /// Authenticate with the Digest scheme, see RFC 7616.
+    ///
+    /// If the server answers `401 Unauthorized` with a `Digest` challenge, the response to the
+    /// challenge is computed and the request is sent again. Streaming bodies can't be sent
+    /// twice, so the `401` response is returned as is for them. The challenge is only answered
+    /// by `send` and `send_async`.
+    ///
+    /// # Optional
+    ///
+    /// This requires the `digest-auth` feature enabled.
+    ///
+    /// ```
+    /// # use anyhow::Result;
+    /// # use waki::Client;
+    /// # fn run() -> Result<()> {
+    /// let resp = Client::new().get("https://httpbin.org/digest-auth/auth/user/passwd")
+    ///     .digest_auth("user", "passwd")
+    ///     .send()?;
+    /// # Ok(())
+    /// # }
+    /// ```
+    #[cfg(feature = "digest-auth")]
+    #[inline]
+    pub fn digest_auth<U: Into<String>, P: Into<String>>(
+        mut self,
+        username: U,
+        password: P,
+    ) -> Self {
+        if let Ok(ref mut req) = self.inner {
+            req.digest_auth = Some((username.into(), password.into()));
+        }
+        self
+    }
+
+    /// Sign the request with `signer` right before it is sent, see [`crate::sign`].
+    ///
+    /// # Optional
+    ///
+    /// This requires the `signing` feature enabled.
+    ///
+    /// ```
+    /// # use anyhow::Result;
+    /// # use waki::{sign::HmacSigner, Client};
+    /// # fn run() -> Result<()> {
+    /// let resp = Client::new().post("https://example.com/hooks")
+    ///     .signer(HmacSigner::new("secret"))
+    ///     .body("hello")
+    ///     .send()?;
+    /// # Ok(())
+    /// # }
+    /// ```
+    #[cfg(feature = "signing")]
+    #[inline]
+    pub fn signer<S: Signer + 'static>(mut self, signer: S) -> Self {
+        if let Ok(ref mut req) = self.inner {
+            req.signer = Some(Arc::new(signer));
+        }
+        self
+    }
+
     /// Build the Request.
     #[inline]
     pub fn build(self) -> Result<Request> {
@@ -109,14 +288,66 @@ impl RequestBuilder {
             Err(e) => Err(e),
         }
     }
+
+    /// Send the Request asynchronously, returning a [`Response`].
+    ///
+    /// # Optional
+    ///
+    /// This requires the `async` feature enabled.
+    ///
+    /// ```
+    /// # use anyhow::Result;
+    /// # use waki::Client;
+    /// # async fn run() -> Result<()> {
+    /// let resp = Client::new().get("https://httpbin.org/get").send_async().await?;
+    /// # Ok(())
+    /// # }
+    /// ```
+    #[cfg(feature = "async")]
+    pub async fn send_async(self) -> Result<Response> {
+        self.inner?.send_async().await
+    }
+
+    /// Send the Request without waiting for the response, returning a [`PendingResponse`].
+    ///
+    /// The request body is written before returning. Dropping the returned handle cancels the
+    /// request.
+    ///
+    /// ```
+    /// # use anyhow::Result;
+    /// # use waki::Client;
+    /// # fn run() -> Result<()> {
+    /// let client = Client::new();
+    /// let first = client.get("https://httpbin.org/get").start()?;
+    /// let second = client.get("https://httpbin.org/ip").start()?;
+    /// // both requests are in flight
+    /// let (first, second) = (first.wait()?, second.wait()?);
+    /// # Ok(())
+    /// # }
+    /// ```
+    #[inline]
+    pub fn start(self) -> Result<PendingResponse> {
+        match self.inner {
+            Ok(req) => req.start(),
+            Err(e) => Err(e),
+        }
+    }
 }
 
 pub struct Request {
-    method: Method,
-    uri: Parts,
+    pub(crate) method: Method,
+    pub(crate) uri: Parts,
     pub(crate) headers: HeaderMap,
     pub(crate) body: Body,
     connect_timeout: Option<u64>,
+    timeout: Option<u64>,
+    pub(crate) token_provider: Option<Arc<dyn TokenProvider>>,
+    #[cfg(feature = "cache")]
+    pub(crate) cache: Option<Arc<dyn CacheStore>>,
+    #[cfg(feature = "digest-auth")]
+    digest_auth: Option<(String, String)>,
+    #[cfg(feature = "signing")]
+    pub(crate) signer: Option<Arc<dyn Signer>>,
 }
 
 impl TryFrom<IncomingRequest> for Request {
@@ -154,13 +385,26 @@ impl TryFrom<IncomingRequest> for Request {
         // The consume() method can only be called once
         let incoming_body = req.consume().unwrap();
         drop(req);
+        let body = Body::Stream(IncomingBodyStream::new(
+            incoming_body,
+            Incoming::Request,
+            &headers,
+        ));
 
         Ok(Self {
             method,
             uri: parts,
             headers,
-            body: Body::Stream(incoming_body.into()),
+            body,
             connect_timeout: None,
+            timeout: None,
+            token_provider: None,
+            #[cfg(feature = "cache")]
+            cache: None,
+            #[cfg(feature = "digest-auth")]
+            digest_auth: None,
+            #[cfg(feature = "signing")]
+            signer: None,
         })
     }
 }
@@ -174,6 +418,14 @@ impl Request {
             headers: HeaderMap::new(),
             body: Body::Bytes(vec![]),
             connect_timeout: None,
+            timeout: None,
+            token_provider: None,
+            #[cfg(feature = "cache")]
+            cache: None,
+            #[cfg(feature = "digest-auth")]
+            digest_auth: None,
+            #[cfg(feature = "signing")]
+            signer: None,
         }
     }
 
@@ -215,19 +467,274 @@ impl Request {
         &self.uri.authority
     }
 
-    fn send(mut self) -> Result<Response> {
-        let req = OutgoingRequest::new(self.headers.try_into()?);
+    /// Get the username and password of the `Authorization` header, `None` if there are no
+    /// valid Basic credentials.
+    ///
+    /// Compare the password with [`constant_time_eq`](crate::auth::constant_time_eq).
+    pub fn basic_auth(&self) -> Option<(String, String)> {
+        match self.authorization()? {
+            Authorization::Basic { username, password } => Some((username, password)),
+            _ => None,
+        }
+    }
+
+    /// Get the token of the `Authorization` header, `None` if there is no Bearer token.
+    pub fn bearer_token(&self) -> Option<String> {
+        match self.authorization()? {
+            Authorization::Bearer(token) => Some(token),
+            _ => None,
+        }
+    }
+
+    /// Pick the media type of `available` preferred by the `Accept` header, following the
+    /// media range matching of RFC 9110 with quality values and specificity.
+    ///
+    /// On equal quality the first available media type wins, which is also picked when there is
+    /// no `Accept` header. `None` means that none is acceptable, see
+    /// [`Response::not_acceptable`].
+    ///
+    /// ```
+    /// use waki::{handler, ErrorCode, Request, Response};
+    ///
+    /// #[handler]
+    /// fn hello(req: Request) -> Result<Response, ErrorCode> {
+    ///     let available = ["application/json", "text/html"];
+    ///     match req.negotiate(&available) {
+    ///         Some("text/html") => Response::builder().body("<p>Hello</p>").build(),
+    ///         Some(_) => Response::builder().body(r#"{"message":"Hello"}"#).build(),
+    ///         None => Ok(Response::not_acceptable(&available)),
+    ///     }
+    /// }
+    /// ```
+    pub fn negotiate<'a>(&self, available: &[&'a str]) -> Option<&'a str> {
+        negotiate::media_type(&self.accept(), available)
+    }
+
+    /// Pick the language tag of `available` preferred by the `Accept-Language` header, a range
+    /// such as `en` matching both `en` and `en-US`.
+    pub fn negotiate_language<'a>(&self, available: &[&'a str]) -> Option<&'a str> {
+        negotiate::language(
+            &parse_quality_list(self.headers.get_all(ACCEPT_LANGUAGE)),
+            available,
+        )
+    }
+
+    /// Pick the content coding of `available` preferred by the `Accept-Encoding` header,
+    /// `identity` being acceptable unless it is explicitly excluded.
+    pub fn negotiate_encoding<'a>(&self, available: &[&'a str]) -> Option<&'a str> {
+        negotiate::encoding(
+            &parse_quality_list(self.headers.get_all(ACCEPT_ENCODING)),
+            available,
+        )
+    }
+
+    /// Evaluate the preconditions of the request against the current `etag` and `last_modified`
+    /// date of the target resource, see RFC 9110 section 13.2.2, returning the response to send
+    /// instead of handling the request: `412 Precondition Failed`, or `304 Not Modified` for
+    /// `GET` and `HEAD` requests.
+    ///
+    /// It must be called before changing the resource, so that a stale `If-Match` or
+    /// `If-Unmodified-Since` prevents the change. `If-None-Match` and `If-Modified-Since` of
+    /// `GET` and `HEAD` requests are also evaluated against the response of the handler by
+    /// [`handler`](crate::handler).
+    ///
+    /// ```
+    /// use waki::{handler, EntityTag, ErrorCode, Request, Response};
+    ///
+    /// #[handler]
+    /// fn update(req: Request) -> Result<Response, ErrorCode> {
+    ///     let current = EntityTag::strong("v1");
+    ///     if let Some(resp) = req.check_preconditions(Some(&current), None) {
+    ///         return Ok(resp);
+    ///     }
+    ///     // update the resource
+    ///     Response::builder().status_code(204).build()
+    /// }
+    /// ```
+    pub fn check_preconditions(
+        &self,
+        etag: Option<&EntityTag>,
+        last_modified: Option<SystemTime>,
+    ) -> Option<Response> {
+        let status_code = Preconditions::all(self).evaluate(etag, last_modified)?;
+        Some(conditional::precondition_response(
+            status_code,
+            etag,
+            last_modified,
+        ))
+    }
+
+    /// Verify the signature of a webhook with `verifier`.
+    ///
+    /// The body is read into memory first with [`Request::buffer`], so that it can still be
+    /// parsed with `json()` or `form()` once verified.
+    ///
+    /// # Optional
+    ///
+    /// This requires the `signing` feature enabled.
+    #[cfg(feature = "signing")]
+    pub fn verify_hmac(&mut self, verifier: &WebhookVerifier) -> Result<()> {
+        self.buffer()?;
+        verifier.verify(&self.headers, self.body_ref().unwrap_or_default())
+    }
+
+    /// Forward the request to the upstream `url`, see [`Client::forward`].
+    #[inline]
+    pub fn forward_to(self, url: &str) -> Result<Response> {
+        Client::new().forward(self, url)
+    }
+
+    pub(crate) fn send(self) -> Result<Response> {
+        #[cfg(feature = "cache")]
+        if let Some(store) = self.cache.clone() {
+            return match cache::lookup(store, self) {
+                Lookup::Hit(resp) => Ok(resp),
+                Lookup::Miss(mut req, pending) => {
+                    req.cache = None;
+                    pending.complete((*req).send())
+                }
+            };
+        }
+        #[cfg(feature = "digest-auth")]
+        if self.digest_auth.is_some() && matches!(self.body, Body::Bytes(_)) {
+            return self.send_digest();
+        }
+        self.start()?.wait()
+    }
+
+    pub(crate) fn start(mut self) -> Result<PendingResponse> {
+        let (outgoing_body, future_response, body, timeout) = self.handle()?;
+        body_to_outgoing_body(&outgoing_body, body)?;
+        OutgoingBody::finish(outgoing_body, None)?;
+
+        Ok(PendingResponse::new(future_response, timeout))
+    }
+
+    #[cfg(feature = "async")]
+    pub(crate) async fn send_async(self) -> Result<Response> {
+        #[cfg(feature = "cache")]
+        if let Some(store) = self.cache.clone() {
+            return match cache::lookup(store, self) {
+                Lookup::Hit(resp) => Ok(resp),
+                Lookup::Miss(mut req, pending) => {
+                    req.cache = None;
+                    pending.complete(Box::pin((*req).send_async()).await)
+                }
+            };
+        }
+        #[cfg(feature = "digest-auth")]
+        if self.digest_auth.is_some() && matches!(self.body, Body::Bytes(_)) {
+            return Box::pin(self.send_digest_async()).await;
+        }
+        self.send_async_once().await
+    }
+
+    #[cfg(feature = "async")]
+    async fn send_async_once(mut self) -> Result<Response> {
+        let (outgoing_body, future_response, body, timeout) = self.handle()?;
+        body_to_outgoing_body_async(&outgoing_body, body).await?;
+        OutgoingBody::finish(outgoing_body, None)?;
+
+        PendingResponse::new(future_response, timeout)
+            .wait_async()
+            .await
+    }
+
+    /// Send the request, then send it again answering the Digest challenge of a `401`
+    /// response.
+    ///
+    /// The body held in memory is kept once written, so that the request is only built again
+    /// when a challenge is received.
+    #[cfg(feature = "digest-auth")]
+    fn send_digest(mut self) -> Result<Response> {
+        let (outgoing_body, future_response, body, timeout) = self.handle()?;
+        let body = body.into_kept_bytes();
+        write_to_outgoing_body(&outgoing_body, &body)?;
+        OutgoingBody::finish(outgoing_body, None)?;
+        let resp = PendingResponse::new(future_response, timeout).wait()?;
+
+        match DigestChallenge::from_response(&resp) {
+            Some(challenge) => {
+                drop(resp);
+                self.body = Body::Bytes(body);
+                self.authorize_digest(&challenge)?;
+                self.start()?.wait()
+            }
+            None => Ok(resp),
+        }
+    }
+
+    #[cfg(all(feature = "digest-auth", feature = "async"))]
+    async fn send_digest_async(mut self) -> Result<Response> {
+        let (outgoing_body, future_response, body, timeout) = self.handle()?;
+        let body = body.into_kept_bytes();
+        write_to_outgoing_body_async(&outgoing_body, &body).await?;
+        OutgoingBody::finish(outgoing_body, None)?;
+        let resp = PendingResponse::new(future_response, timeout)
+            .wait_async()
+            .await?;
+
+        match DigestChallenge::from_response(&resp) {
+            Some(challenge) => {
+                drop(resp);
+                self.body = Body::Bytes(body);
+                self.authorize_digest(&challenge)?;
+                self.send_async_once().await
+            }
+            None => Ok(resp),
+        }
+    }
+
+    /// Set the `Authorization` header answering a Digest challenge.
+    #[cfg(feature = "digest-auth")]
+    fn authorize_digest(&mut self, challenge: &DigestChallenge) -> Result<()> {
+        let (Some((username, password)), Body::Bytes(body)) = (&self.digest_auth, &self.body)
+        else {
+            unreachable!("only requests with credentials and a bytes body are sent again");
+        };
+        let uri = self.uri.path_and_query.as_ref().map_or("/", |p| p.as_str());
+        let credentials = challenge.authorize(username, password, &self.method, uri, body)?;
+        self.headers.insert(AUTHORIZATION, credentials.try_into()?);
+        Ok(())
+    }
+
+    /// Hand the request over to the host, returning what is needed to write the body and wait
+    /// for the response.
+    ///
+    /// The body is taken out of the request, which can then be handed over again with another
+    /// body.
+    fn handle(&mut self) -> Result<(OutgoingBody, FutureIncomingResponse, Body, Option<u64>)> {
+        if let Some(provider) = &self.token_provider {
+            if !self.headers.contains_key(AUTHORIZATION) {
+                let token = provider.token()?;
+                self.headers.insert(
+                    AUTHORIZATION,
+                    Authorization::bearer(token).to_string().try_into()?,
+                );
+            }
+        }
+        // the headers are final, sign them
+        #[cfg(feature = "signing")]
+        if let Some(signer) = &self.signer {
+            signer.sign(&mut SignableRequest {
+                method: &self.method,
+                uri: &self.uri,
+                headers: &mut self.headers,
+                body: &mut self.body,
+            })?;
+        }
+        let req = OutgoingRequest::new((&self.headers).try_into()?);
         req.set_method(&self.method)
             .map_err(|()| anyhow!("failed to set method"))?;
-        if let Some(scheme) = self.uri.scheme {
+        if let Some(scheme) = &self.uri.scheme {
             req.set_scheme(Some(&scheme.as_str().into()))
                 .map_err(|()| anyhow!("failed to set scheme"))?;
         }
-        if let Some(authority) = self.uri.authority {
+        if let Some(authority) = &self.uri.authority {
             req.set_authority(Some(authority.as_str()))
                 .map_err(|()| anyhow!("failed to set authority"))?;
         }
-        if let Some(path_and_query) = self.uri.path_and_query {
+        if let Some(path_and_query) = &self.uri.path_and_query {
             req.set_path_with_query(Some(path_and_query.as_str()))
                 .map_err(|()| anyhow!("failed to set path_with_query"))?;
         }
@@ -242,32 +749,7 @@ impl Request {
             .map_err(|()| anyhow!("failed to set connect_timeout"))?;
         let future_response = outgoing_handler::handle(req, Some(options))?;
 
-        // Handle body - stream if it's a reader, otherwise load bytes
-        match &mut self.body {
-            Body::Reader(reader) => {
-                stream_to_outgoing_body(&outgoing_body, reader.as_mut())?;
-            }
-            _ => {
-                let body = self.body.bytes()?;
-                write_to_outgoing_body(&outgoing_body, body.as_slice())?;
-            }
-        }
-        OutgoingBody::finish(outgoing_body, None)?;
-
-        let incoming_response = match future_response.get() {
-            Some(result) => result.map_err(|()| anyhow!("response already taken"))?,
-            None => {
-                let pollable = future_response.subscribe();
-                pollable.block();
-
-                future_response
-                    .get()
-                    .expect("incoming response available")
-                    .map_err(|()| anyhow!("response already taken"))?
-            }
-        }?;
-        drop(future_response);
-
-        incoming_response.try_into()
+        let body = std::mem::replace(&mut self.body, Body::Bytes(vec![]));
+        Ok((outgoing_body, future_response, body, self.timeout))
     }
 }
@@ -2,12 +2,17 @@ use crate::{
     bindings::wasi::http::types::{
         IncomingResponse, OutgoingBody, OutgoingResponse, ResponseOutparam,
     },
-    body::{write_to_outgoing_body, Body},
-    header::HeaderMap,
-    ErrorCode,
+    body::{body_to_outgoing_body, Body, Incoming, IncomingBodyStream},
+    conditional::Preconditions,
+    header::{HeaderMap, HeaderValue, CONTENT_TYPE, ETAG, LAST_MODIFIED},
+    EntityTag, ErrorCode,
 };
 
+#[cfg(feature = "async")]
+use crate::body::body_to_outgoing_body_async;
+
 use anyhow::{Error, Result};
+use std::time::SystemTime;
 
 pub struct ResponseBuilder {
     // all errors generated while building the response will be deferred.
@@ -40,6 +45,50 @@ impl ResponseBuilder {
         self
     }
 
+    /// Set a plain text body, with the `text/plain; charset=utf-8` content type.
+    ///
+    /// ```
+    /// # use waki::{ErrorCode, Response};
+    /// # fn run() -> Result<Response, ErrorCode> {
+    /// Response::builder().text("Hello, WASI!").build()
+    /// # }
+    /// ```
+    #[inline]
+    pub fn text<V: Into<String>>(self, text: V) -> Self {
+        self.content_type(mime::TEXT_PLAIN_UTF_8).body(text.into())
+    }
+
+    /// Set the `ETag` header.
+    ///
+    /// `GET` and `HEAD` requests with a matching `If-None-Match` header are then answered with
+    /// `304 Not Modified`, see [`EntityTag::for_bytes`] to compute a tag from the body. The
+    /// `If-Match` header is checked by [`Request::check_preconditions`](crate::Request::check_preconditions).
+    ///
+    /// ```
+    /// # use waki::{EntityTag, ErrorCode, Response};
+    /// # fn run() -> Result<Response, ErrorCode> {
+    /// let body = b"Hello, WASI!";
+    /// Response::builder()
+    ///     .etag(EntityTag::for_bytes(body))
+    ///     .body(body)
+    ///     .build()
+    /// # }
+    /// ```
+    #[inline]
+    pub fn etag(self, etag: EntityTag) -> Self {
+        self.header(ETAG, etag.to_string())
+    }
+
+    /// Set the `Last-Modified` header.
+    ///
+    /// `GET` and `HEAD` requests with an `If-Modified-Since` header are then answered with
+    /// `304 Not Modified` when the date is not later. The `If-Unmodified-Since` header is checked
+    /// by [`Request::check_preconditions`](crate::Request::check_preconditions).
+    #[inline]
+    pub fn last_modified(self, date: SystemTime) -> Self {
+        self.header(LAST_MODIFIED, httpdate::fmt_http_date(date))
+    }
+
     /// Build the Response.
     #[inline]
     pub fn build(self) -> Result<Response, ErrorCode> {
@@ -53,7 +102,7 @@ impl ResponseBuilder {
 pub struct Response {
     pub(crate) headers: HeaderMap,
     pub(crate) body: Body,
-    status_code: u16,
+    pub(crate) status_code: u16,
 }
 
 impl Default for Response {
@@ -72,11 +121,16 @@ impl TryFrom<IncomingResponse> for Response {
         // The consume() method can only be called once
         let incoming_body = incoming_response.consume().unwrap();
         drop(incoming_response);
+        let body = Body::Stream(IncomingBodyStream::new(
+            incoming_body,
+            Incoming::Response,
+            &headers,
+        ));
 
         Ok(Self {
             headers,
             status_code,
-            body: Body::Stream(incoming_body.into()),
+            body,
         })
     }
 }
@@ -96,14 +150,42 @@ impl Response {
         ResponseBuilder::new()
     }
 
+    /// Create a `406 Not Acceptable` response listing the `available` media types, for when
+    /// [`Request::negotiate`](crate::Request::negotiate) finds none acceptable.
+    pub fn not_acceptable(available: &[&str]) -> Self {
+        let mut resp = Self::new();
+        resp.status_code = 406;
+        resp.headers.insert(
+            CONTENT_TYPE,
+            HeaderValue::from_static("text/plain; charset=utf-8"),
+        );
+        resp.body = Body::Bytes(format!("Available: {}\n", available.join(", ")).into());
+        resp
+    }
+
     #[inline]
     /// Get the status code of the response.
     pub fn status_code(&self) -> u16 {
         self.status_code
     }
+
+    /// Get the `ETag` header, `None` if it is missing or invalid.
+    pub fn etag(&self) -> Option<EntityTag> {
+        self.headers.get(ETAG)?.to_str().ok()?.parse().ok()
+    }
+
+    /// Get the `Last-Modified` header, `None` if it is missing or invalid.
+    pub fn last_modified(&self) -> Option<SystemTime> {
+        httpdate::parse_http_date(self.headers.get(LAST_MODIFIED)?.to_str().ok()?).ok()
+    }
 }
 
-pub fn handle_response(response_out: ResponseOutparam, response: Response) {
+pub fn handle_response(
+    response_out: ResponseOutparam,
+    response: Response,
+    preconditions: Preconditions,
+) {
+    let response = preconditions.apply(response);
     let outgoing_response = OutgoingResponse::new(response.headers.try_into().unwrap());
     outgoing_response
         .set_status_code(response.status_code)
@@ -111,7 +193,26 @@ pub fn handle_response(response_out: ResponseOutparam, response: Response) {
     let outgoing_body = outgoing_response.body().unwrap();
     ResponseOutparam::set(response_out, Ok(outgoing_response));
 
-    let body = response.body.bytes().unwrap();
-    write_to_outgoing_body(&outgoing_body, body.as_slice()).unwrap();
+    body_to_outgoing_body(&outgoing_body, response.body).unwrap();
+    OutgoingBody::finish(outgoing_body, None).unwrap();
+}
+
+#[cfg(feature = "async")]
+pub async fn handle_response_async(
+    response_out: ResponseOutparam,
+    response: Response,
+    preconditions: Preconditions,
+) {
+    let response = preconditions.apply(response);
+    let outgoing_response = OutgoingResponse::new(response.headers.try_into().unwrap());
+    outgoing_response
+        .set_status_code(response.status_code)
+        .unwrap();
+    let outgoing_body = outgoing_response.body().unwrap();
+    ResponseOutparam::set(response_out, Ok(outgoing_response));
+
+    body_to_outgoing_body_async(&outgoing_body, response.body)
+        .await
+        .unwrap();
     OutgoingBody::finish(outgoing_body, None).unwrap();
 }
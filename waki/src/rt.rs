@@ -0,0 +1,256 @@
+//! A tiny single-threaded executor multiplexing WASI pollables.
+//!
+//! Futures waiting for I/O register the pollables they are interested in, and [`block_on`]
+//! calls `wasi:io/poll` on all of them at once whenever no task can make progress.
+//!
+//! ```
+//! # use anyhow::Result;
+//! # use waki::{rt, Client};
+//! # fn run() -> Result<()> {
+//! rt::block_on(async {
+//!     let client = Client::new();
+//!     // both requests are in flight at the same time
+//!     let first = rt::spawn(client.get("https://httpbin.org/get").send_async());
+//!     let second = rt::spawn(client.get("https://httpbin.org/ip").send_async());
+//!     let (first, second) = (first.await?, second.await?);
+//!     Ok(())
+//! })
+//! # }
+//! ```
+
+use crate::bindings::wasi::io::poll::{poll, Pollable};
+
+use std::cell::RefCell;
+use std::future::Future;
+use std::pin::{pin, Pin};
+use std::rc::Rc;
+use std::sync::{
+    atomic::{AtomicBool, Ordering},
+    Arc,
+};
+use std::task::{Context, Poll, Wake, Waker};
+
+thread_local! {
+    static REACTOR: RefCell<Reactor> = RefCell::new(Reactor::default());
+    static TASKS: RefCell<Vec<Task>> = const { RefCell::new(Vec::new()) };
+}
+
+#[derive(Default)]
+struct Reactor {
+    // slab of registered pollables, indexed by the key held by the waiting future
+    entries: Vec<Option<Entry>>,
+}
+
+struct Entry {
+    pollable: Pollable,
+    waker: Option<Waker>,
+}
+
+impl Reactor {
+    fn register(&mut self, pollable: Pollable) -> usize {
+        let entry = Some(Entry {
+            pollable,
+            waker: None,
+        });
+        match self.entries.iter().position(Option::is_none) {
+            Some(key) => {
+                self.entries[key] = entry;
+                key
+            }
+            None => {
+                self.entries.push(entry);
+                self.entries.len() - 1
+            }
+        }
+    }
+
+    /// Block until at least one registered pollable is ready, and wake its waiter.
+    fn block(&mut self) {
+        let (keys, pollables): (Vec<_>, Vec<_>) = self
+            .entries
+            .iter()
+            .enumerate()
+            .filter_map(|(key, entry)| {
+                let entry = entry.as_ref()?;
+                entry.waker.as_ref()?;
+                Some((key, &entry.pollable))
+            })
+            .unzip();
+        assert!(
+            !pollables.is_empty(),
+            "deadlock: no task can make progress and no I/O is pending"
+        );
+
+        for ready in poll(&pollables) {
+            if let Some(waker) = self.entries[keys[ready as usize]]
+                .as_mut()
+                .and_then(|entry| entry.waker.take())
+            {
+                waker.wake();
+            }
+        }
+    }
+}
+
+/// A future resolving once any of its pollables is ready.
+struct WaitAny {
+    keys: Vec<usize>,
+}
+
+impl Future for WaitAny {
+    type Output = ();
+
+    fn poll(self: Pin<&mut Self>, cx: &mut Context<'_>) -> Poll<()> {
+        REACTOR.with_borrow_mut(|reactor| {
+            for key in &self.keys {
+                if let Some(entry) = &reactor.entries[*key] {
+                    if entry.pollable.ready() {
+                        return Poll::Ready(());
+                    }
+                }
+            }
+            for key in &self.keys {
+                if let Some(entry) = &mut reactor.entries[*key] {
+                    entry.waker = Some(cx.waker().clone());
+                }
+            }
+            Poll::Pending
+        })
+    }
+}
+
+impl Drop for WaitAny {
+    fn drop(&mut self) {
+        REACTOR.with_borrow_mut(|reactor| {
+            for key in &self.keys {
+                reactor.entries[*key] = None;
+            }
+        })
+    }
+}
+
+/// Wait until the pollable is ready.
+pub async fn wait(pollable: Pollable) {
+    wait_any(vec![pollable]).await
+}
+
+/// Wait until any of the pollables is ready.
+pub async fn wait_any(pollables: Vec<Pollable>) {
+    let keys = REACTOR.with_borrow_mut(|reactor| {
+        pollables
+            .into_iter()
+            .map(|pollable| reactor.register(pollable))
+            .collect()
+    });
+    WaitAny { keys }.await
+}
+
+#[derive(Default)]
+struct Flag(AtomicBool);
+
+impl Flag {
+    fn take(&self) -> bool {
+        self.0.swap(false, Ordering::AcqRel)
+    }
+}
+
+impl Wake for Flag {
+    fn wake(self: Arc<Self>) {
+        self.0.store(true, Ordering::Release)
+    }
+}
+
+struct Task {
+    future: Pin<Box<dyn Future<Output = ()>>>,
+    woken: Arc<Flag>,
+}
+
+/// Run spawned tasks which have been woken, returning whether any of them was polled.
+fn run_tasks() -> bool {
+    let mut tasks = TASKS.take();
+    let mut progressed = false;
+    tasks.retain_mut(|task| {
+        if !task.woken.take() {
+            return true;
+        }
+        progressed = true;
+        let waker = Waker::from(task.woken.clone());
+        task.future
+            .as_mut()
+            .poll(&mut Context::from_waker(&waker))
+            .is_pending()
+    });
+    // keep the tasks spawned while running the others
+    TASKS.with_borrow_mut(|spawned| tasks.append(spawned));
+    TASKS.set(tasks);
+    progressed
+}
+
+/// Run a future to completion, driving the spawned tasks and WASI I/O along the way.
+///
+/// Spawned tasks which are still running when the future completes are resumed by the next
+/// call.
+pub fn block_on<F: Future>(future: F) -> F::Output {
+    let mut future = pin!(future);
+    let woken = Arc::new(Flag(AtomicBool::new(true)));
+    let waker = Waker::from(woken.clone());
+    let mut cx = Context::from_waker(&waker);
+
+    loop {
+        if woken.take() {
+            if let Poll::Ready(output) = future.as_mut().poll(&mut cx) {
+                return output;
+            }
+        }
+        if !run_tasks() && !woken.0.load(Ordering::Acquire) {
+            REACTOR.with_borrow_mut(Reactor::block);
+        }
+    }
+}
+
+/// A handle to the output of a spawned task, resolving once the task completes.
+pub struct JoinHandle<T> {
+    state: Rc<RefCell<(Option<T>, Option<Waker>)>>,
+}
+
+impl<T> Future for JoinHandle<T> {
+    type Output = T;
+
+    fn poll(self: Pin<&mut Self>, cx: &mut Context<'_>) -> Poll<T> {
+        let mut state = self.state.borrow_mut();
+        match state.0.take() {
+            Some(output) => Poll::Ready(output),
+            None => {
+                state.1 = Some(cx.waker().clone());
+                Poll::Pending
+            }
+        }
+    }
+}
+
+/// Spawn a task running concurrently with the future passed to [`block_on`].
+pub fn spawn<F>(future: F) -> JoinHandle<F::Output>
+where
+    F: Future + 'static,
+    F::Output: 'static,
+{
+    let state = Rc::new(RefCell::new((None, None::<Waker>)));
+    let handle = JoinHandle {
+        state: state.clone(),
+    };
+    let future = async move {
+        let output = future.await;
+        let mut state = state.borrow_mut();
+        state.0 = Some(output);
+        if let Some(waker) = state.1.take() {
+            waker.wake();
+        }
+    };
+    TASKS.with_borrow_mut(|tasks| {
+        tasks.push(Task {
+            future: Box::pin(future),
+            woken: Arc::new(Flag(AtomicBool::new(true))),
+        })
+    });
+    handle
+}
@@ -0,0 +1,444 @@
+use crate::header::{GetAll, HeaderValue};
+
+use anyhow::{anyhow, Error, Result};
+use base64::{engine::general_purpose::STANDARD, Engine};
+use std::fmt;
+use std::str::FromStr;
+
+/// The credentials of an `Authorization` header.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub enum Authorization {
+    /// `Basic` credentials, see RFC 7617.
+    Basic { username: String, password: String },
+    /// A `Bearer` token, see RFC 6750.
+    Bearer(String),
+    /// Any other scheme, with its credentials kept as is.
+    Other { scheme: String, credentials: String },
+}
+
+impl Authorization {
+    #[inline]
+    pub fn basic<U: Into<String>, P: Into<String>>(username: U, password: P) -> Self {
+        Self::Basic {
+            username: username.into(),
+            password: password.into(),
+        }
+    }
+
+    #[inline]
+    pub fn bearer<T: Into<String>>(token: T) -> Self {
+        Self::Bearer(token.into())
+    }
+
+    /// Get the authentication scheme, e.g. `Basic`.
+    pub fn scheme(&self) -> &str {
+        match self {
+            Self::Basic { .. } => "Basic",
+            Self::Bearer(_) => "Bearer",
+            Self::Other { scheme, .. } => scheme,
+        }
+    }
+}
+
+impl fmt::Display for Authorization {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            Self::Basic { username, password } => {
+                write!(
+                    f,
+                    "Basic {}",
+                    STANDARD.encode(format!("{username}:{password}"))
+                )
+            }
+            Self::Bearer(token) => write!(f, "Bearer {token}"),
+            Self::Other {
+                scheme,
+                credentials,
+            } => write!(f, "{scheme} {credentials}"),
+        }
+    }
+}
+
+impl FromStr for Authorization {
+    type Err = Error;
+
+    fn from_str(s: &str) -> Result<Self> {
+        let (scheme, credentials) = s.trim().split_once(' ').unwrap_or((s.trim(), ""));
+        let credentials = credentials.trim();
+        if scheme.eq_ignore_ascii_case("basic") {
+            let decoded = String::from_utf8(STANDARD.decode(credentials)?)?;
+            let (username, password) = decoded
+                .split_once(':')
+                .ok_or_else(|| anyhow!("invalid Basic credentials"))?;
+            Ok(Self::basic(username, password))
+        } else if scheme.eq_ignore_ascii_case("bearer") {
+            if credentials.is_empty() {
+                return Err(anyhow!("missing Bearer token"));
+            }
+            Ok(Self::bearer(credentials))
+        } else if scheme.is_empty() {
+            Err(anyhow!("missing authentication scheme"))
+        } else {
+            Ok(Self::Other {
+                scheme: scheme.to_string(),
+                credentials: credentials.to_string(),
+            })
+        }
+    }
+}
+
+/// An item of a list header weighted by a quality value, such as `text/html;q=0.8` in
+/// `Accept` or `en;q=0.5` in `Accept-Language`.
+#[derive(Debug, Clone, PartialEq)]
+pub struct QualityItem<T> {
+    pub item: T,
+    /// The quality value, between 0 and 1.
+    pub quality: f32,
+}
+
+impl<T> QualityItem<T> {
+    #[inline]
+    pub fn new(item: T, quality: f32) -> Self {
+        Self { item, quality }
+    }
+}
+
+impl<T: fmt::Display> fmt::Display for QualityItem<T> {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        write!(f, "{}", self.item)?;
+        if self.quality < 1.0 {
+            // at most three decimal digits are allowed
+            let q = format!("{:.3}", self.quality);
+            write!(f, ";q={}", q.trim_end_matches('0').trim_end_matches('.'))?;
+        }
+        Ok(())
+    }
+}
+
+impl<T> FromStr for QualityItem<T>
+where
+    T: FromStr,
+    <T as FromStr>::Err: Into<Error>,
+{
+    type Err = Error;
+
+    fn from_str(s: &str) -> Result<Self> {
+        let mut item = vec![];
+        let mut quality = 1.0;
+        for param in s.split(';') {
+            match param.trim().split_once('=') {
+                Some((name, value)) if name.trim().eq_ignore_ascii_case("q") => {
+                    quality = value.trim().parse::<f32>()?;
+                    if !(0.0..=1.0).contains(&quality) {
+                        return Err(anyhow!("invalid quality value: {s}"));
+                    }
+                }
+                _ => item.push(param.trim()),
+            }
+        }
+        let item = item.join(";").parse::<T>().map_err(|e| e.into())?;
+        Ok(Self::new(item, quality))
+    }
+}
+
+/// Parse a comma separated list of quality items spread over several header lines, skipping the
+/// invalid ones, and sort it by descending quality.
+pub(crate) fn parse_quality_list<T>(values: GetAll<'_, HeaderValue>) -> Vec<QualityItem<T>>
+where
+    T: FromStr,
+    <T as FromStr>::Err: Into<Error>,
+{
+    let mut items = values
+        .iter()
+        .filter_map(|v| v.to_str().ok())
+        .flat_map(|v| v.split(','))
+        .filter(|v| !v.trim().is_empty())
+        .filter_map(|v| v.parse::<QualityItem<T>>().ok())
+        .collect::<Vec<_>>();
+    // the sort is stable, so items with the same quality keep their order
+    items.sort_by(|a, b| b.quality.total_cmp(&a.quality));
+    items
+}
+
+/// An entity tag, as found in `ETag`, `If-Match` and `If-None-Match` headers.
+#[derive(Debug, Clone, PartialEq, Eq, Hash)]
+pub struct EntityTag {
+    weak: bool,
+    tag: String,
+}
+
+impl EntityTag {
+    /// Create a strong entity tag, `tag` must not contain double quotes.
+    #[inline]
+    pub fn strong<T: Into<String>>(tag: T) -> Self {
+        Self {
+            weak: false,
+            tag: tag.into(),
+        }
+    }
+
+    /// Create a weak entity tag, `tag` must not contain double quotes.
+    #[inline]
+    pub fn weak<T: Into<String>>(tag: T) -> Self {
+        Self {
+            weak: true,
+            tag: tag.into(),
+        }
+    }
+
+    /// Compute a strong entity tag from the bytes of a body, changing whenever they do.
+    ///
+    /// The tag combines the length of the body with its 64-bit FNV-1a hash, which is stable
+    /// across instances of a component but not collision-resistant.
+    pub fn for_bytes(data: &[u8]) -> Self {
+        let hash = data.iter().fold(0xcbf29ce484222325u64, |hash, &b| {
+            (hash ^ u64::from(b)).wrapping_mul(0x100000001b3)
+        });
+        Self::strong(format!("{:x}-{hash:016x}", data.len()))
+    }
+
+    #[inline]
+    pub fn is_weak(&self) -> bool {
+        self.weak
+    }
+
+    /// Get the opaque tag, without quotes.
+    #[inline]
+    pub fn tag(&self) -> &str {
+        &self.tag
+    }
+
+    /// Strong comparison, see RFC 9110 section 8.8.3.2: both tags must be strong and equal.
+    #[inline]
+    pub fn strong_eq(&self, other: &EntityTag) -> bool {
+        !self.weak && !other.weak && self.tag == other.tag
+    }
+
+    /// Weak comparison, see RFC 9110 section 8.8.3.2: the tags must be equal, whether they are
+    /// weak or not.
+    #[inline]
+    pub fn weak_eq(&self, other: &EntityTag) -> bool {
+        self.tag == other.tag
+    }
+}
+
+impl fmt::Display for EntityTag {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        if self.weak {
+            f.write_str("W/")?;
+        }
+        write!(f, "\"{}\"", self.tag)
+    }
+}
+
+impl FromStr for EntityTag {
+    type Err = Error;
+
+    fn from_str(s: &str) -> Result<Self> {
+        let s = s.trim();
+        let (weak, quoted) = match s.strip_prefix("W/") {
+            Some(quoted) => (true, quoted),
+            None => (false, s),
+        };
+        let tag = quoted
+            .strip_prefix('"')
+            .and_then(|v| v.strip_suffix('"'))
+            .filter(|v| !v.contains('"'))
+            .ok_or_else(|| anyhow!("invalid entity tag: {s}"))?;
+        Ok(Self {
+            weak,
+            tag: tag.to_string(),
+        })
+    }
+}
+
+/// The value of an `If-None-Match` header.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub enum IfNoneMatch {
+    /// `*`, matching any current representation.
+    Any,
+    Tags(Vec<EntityTag>),
+}
+
+impl IfNoneMatch {
+    /// Check whether `etag` matches one of the tags, using the weak comparison.
+    pub fn matches(&self, etag: &EntityTag) -> bool {
+        match self {
+            Self::Any => true,
+            Self::Tags(tags) => tags.iter().any(|tag| tag.weak_eq(etag)),
+        }
+    }
+
+    /// Parse a list of entity tags spread over several header lines.
+    pub(crate) fn from_values(values: GetAll<'_, HeaderValue>) -> Result<Option<Self>> {
+        Ok(parse_tag_values(values)?.map(|tags| tags.map_or(Self::Any, Self::Tags)))
+    }
+}
+
+impl fmt::Display for IfNoneMatch {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            Self::Any => f.write_str("*"),
+            Self::Tags(tags) => fmt_tags(f, tags),
+        }
+    }
+}
+
+impl FromStr for IfNoneMatch {
+    type Err = Error;
+
+    fn from_str(s: &str) -> Result<Self> {
+        Ok(parse_tags(s)?.map_or(Self::Any, Self::Tags))
+    }
+}
+
+/// The value of an `If-Match` header.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub enum IfMatch {
+    /// `*`, matching any current representation.
+    Any,
+    Tags(Vec<EntityTag>),
+}
+
+impl IfMatch {
+    /// Check whether `etag` matches one of the tags, using the strong comparison.
+    pub fn matches(&self, etag: &EntityTag) -> bool {
+        match self {
+            Self::Any => true,
+            Self::Tags(tags) => tags.iter().any(|tag| tag.strong_eq(etag)),
+        }
+    }
+
+    /// Parse a list of entity tags spread over several header lines.
+    pub(crate) fn from_values(values: GetAll<'_, HeaderValue>) -> Result<Option<Self>> {
+        Ok(parse_tag_values(values)?.map(|tags| tags.map_or(Self::Any, Self::Tags)))
+    }
+}
+
+impl fmt::Display for IfMatch {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            Self::Any => f.write_str("*"),
+            Self::Tags(tags) => fmt_tags(f, tags),
+        }
+    }
+}
+
+impl FromStr for IfMatch {
+    type Err = Error;
+
+    fn from_str(s: &str) -> Result<Self> {
+        Ok(parse_tags(s)?.map_or(Self::Any, Self::Tags))
+    }
+}
+
+fn fmt_tags(f: &mut fmt::Formatter<'_>, tags: &[EntityTag]) -> fmt::Result {
+    let tags = tags.iter().map(|tag| tag.to_string()).collect::<Vec<_>>();
+    f.write_str(&tags.join(", "))
+}
+
+/// Parse a list of entity tags, `None` for `*`.
+fn parse_tags(s: &str) -> Result<Option<Vec<EntityTag>>> {
+    if s.trim() == "*" {
+        return Ok(None);
+    }
+    let tags = s
+        .split(',')
+        .filter(|v| !v.trim().is_empty())
+        .map(|v| v.parse())
+        .collect::<Result<Vec<_>>>()?;
+    Ok(Some(tags))
+}
+
+/// Parse a list of entity tags spread over several header lines, `Some(None)` for `*` and
+/// `None` if there are no tags.
+fn parse_tag_values(values: GetAll<'_, HeaderValue>) -> Result<Option<Option<Vec<EntityTag>>>> {
+    let mut tags = vec![];
+    for value in values.iter() {
+        match parse_tags(value.to_str()?)? {
+            None => return Ok(Some(None)),
+            Some(more) => tags.extend(more),
+        }
+    }
+    Ok((!tags.is_empty()).then_some(Some(tags)))
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::header::{HeaderMap, ACCEPT, IF_NONE_MATCH};
+    use mime::Mime;
+
+    #[test]
+    fn test_authorization() -> Result<()> {
+        // example of RFC 7617
+        let basic = "Basic QWxhZGRpbjpvcGVuIHNlc2FtZQ==".parse::<Authorization>()?;
+        assert_eq!(basic, Authorization::basic("Aladdin", "open sesame"));
+        assert_eq!(basic.to_string(), "Basic QWxhZGRpbjpvcGVuIHNlc2FtZQ==");
+
+        let bearer = "bearer mF_9.B5f-4.1JqM".parse::<Authorization>()?;
+        assert_eq!(bearer, Authorization::bearer("mF_9.B5f-4.1JqM"));
+        assert_eq!(bearer.to_string(), "Bearer mF_9.B5f-4.1JqM");
+
+        let digest = r#"Digest username="Mufasa", realm="test""#.parse::<Authorization>()?;
+        assert_eq!(digest.scheme(), "Digest");
+
+        assert!("Basic not-base64!".parse::<Authorization>().is_err());
+        assert!("Bearer".parse::<Authorization>().is_err());
+        Ok(())
+    }
+
+    #[test]
+    fn test_accept() -> Result<()> {
+        let mut headers = HeaderMap::new();
+        headers.append(ACCEPT, "text/html, application/json;q=0.9".parse()?);
+        headers.append(ACCEPT, "*/*;q=0.1, text/plain;format=flowed;q=0.9".parse()?);
+
+        let accept = parse_quality_list::<Mime>(headers.get_all(ACCEPT));
+        let items = accept.iter().map(|v| v.to_string()).collect::<Vec<_>>();
+        assert_eq!(
+            items,
+            [
+                "text/html",
+                "application/json;q=0.9",
+                "text/plain;format=flowed;q=0.9",
+                "*/*;q=0.1",
+            ]
+        );
+        assert!("text/html;q=2".parse::<QualityItem<Mime>>().is_err());
+        Ok(())
+    }
+
+    #[test]
+    fn test_if_none_match() -> Result<()> {
+        let mut headers = HeaderMap::new();
+        headers.append(IF_NONE_MATCH, r#""xyzzy", W/"r2d2xxxx""#.parse()?);
+        headers.append(IF_NONE_MATCH, r#""c3piozzzz""#.parse()?);
+
+        let if_none_match = IfNoneMatch::from_values(headers.get_all(IF_NONE_MATCH))?.unwrap();
+        assert_eq!(
+            if_none_match.to_string(),
+            r#""xyzzy", W/"r2d2xxxx", "c3piozzzz""#
+        );
+        assert!(if_none_match.matches(&EntityTag::strong("r2d2xxxx")));
+        assert!(!if_none_match.matches(&EntityTag::strong("other")));
+        assert!(IfNoneMatch::Any.matches(&EntityTag::weak("other")));
+
+        assert!(!EntityTag::weak("1").strong_eq(&EntityTag::weak("1")));
+        assert!(EntityTag::weak("1").weak_eq(&EntityTag::strong("1")));
+        assert!("no-quotes".parse::<EntityTag>().is_err());
+
+        let if_match = r#""xyzzy", W/"r2d2xxxx""#.parse::<IfMatch>()?;
+        assert!(if_match.matches(&EntityTag::strong("xyzzy")));
+        assert!(!if_match.matches(&EntityTag::strong("r2d2xxxx")));
+        assert!(!if_match.matches(&EntityTag::weak("xyzzy")));
+        assert_eq!(if_match.to_string(), r#""xyzzy", W/"r2d2xxxx""#);
+        assert_eq!("*".parse::<IfMatch>()?, IfMatch::Any);
+
+        let etag = EntityTag::for_bytes(b"hello");
+        assert!(!etag.is_weak());
+        assert_eq!(etag.tag(), "5-a430d84680aabd0b");
+        assert_ne!(etag, EntityTag::for_bytes(b"hellp"));
+        Ok(())
+    }
+}
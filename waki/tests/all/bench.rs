@@ -0,0 +1,38 @@
+//! Throughput comparisons of the body copy paths, run with `cargo test -- --ignored bench`.
+
+use super::run_wasi_http;
+
+use anyhow::Result;
+use http_body_util::{combinators::BoxBody, BodyExt, Full};
+use std::time::Instant;
+
+const BODY_SIZE: usize = 64 * 1024 * 1024;
+
+async fn echo_throughput(component_filename: &str) -> Result<f64> {
+    let req = hyper::Request::builder()
+        .uri("http://localhost")
+        .body(BoxBody::new(
+            Full::new(vec![b'x'; BODY_SIZE].into()).map_err(|_| unreachable!()),
+        ))?;
+
+    let start = Instant::now();
+    let resp = run_wasi_http(component_filename, req).await??;
+    let elapsed = start.elapsed();
+    assert_eq!(resp.into_body().to_bytes().len(), BODY_SIZE);
+
+    Ok(BODY_SIZE as f64 / 1024.0 / 1024.0 / elapsed.as_secs_f64())
+}
+
+#[tokio::test(flavor = "multi_thread")]
+#[ignore]
+async fn bench_echo() -> Result<()> {
+    let buffered = echo_throughput(test_programs_artifacts::SERVER_ECHO_BUFFERED_COMPONENT).await?;
+    let stream = echo_throughput(test_programs_artifacts::SERVER_ECHO_STREAM_COMPONENT).await?;
+    let splice = echo_throughput(test_programs_artifacts::SERVER_ECHO_SPLICE_COMPONENT).await?;
+    println!(
+        "echo {BODY_SIZE} bytes, buffered: {buffered:.1} MiB/s, stream: {stream:.1} MiB/s, \
+         splice: {splice:.1} MiB/s"
+    );
+
+    Ok(())
+}
@@ -1,4 +1,4 @@
-use super::run_wasi;
+use super::{run_wasi, run_wasi_with_env};
 
 #[tokio::test(flavor = "multi_thread")]
 async fn get_chunk() {
@@ -14,6 +14,13 @@ async fn get_headers() {
         .unwrap();
 }
 
+#[tokio::test(flavor = "multi_thread")]
+async fn get_reader() {
+    run_wasi(test_programs_artifacts::CLIENT_GET_READER_COMPONENT)
+        .await
+        .unwrap();
+}
+
 #[tokio::test(flavor = "multi_thread")]
 async fn get_with_query() {
     run_wasi(test_programs_artifacts::CLIENT_GET_WITH_QUERY_COMPONENT)
@@ -48,3 +55,109 @@ async fn post_with_multipart_form_data() {
         .await
         .unwrap();
 }
+
+#[tokio::test(flavor = "multi_thread")]
+async fn auth() {
+    run_wasi(test_programs_artifacts::CLIENT_AUTH_COMPONENT)
+        .await
+        .unwrap();
+}
+
+#[tokio::test(flavor = "multi_thread")]
+async fn send_async() {
+    run_wasi(test_programs_artifacts::CLIENT_SEND_ASYNC_COMPONENT)
+        .await
+        .unwrap();
+}
+
+#[tokio::test(flavor = "multi_thread")]
+async fn send_all() {
+    run_wasi(test_programs_artifacts::CLIENT_SEND_ALL_COMPONENT)
+        .await
+        .unwrap();
+}
+
+#[tokio::test(flavor = "multi_thread")]
+async fn sign() {
+    run_wasi(test_programs_artifacts::CLIENT_SIGN_COMPONENT)
+        .await
+        .unwrap();
+}
+
+#[tokio::test(flavor = "multi_thread")]
+async fn download() {
+    run_wasi(test_programs_artifacts::CLIENT_DOWNLOAD_COMPONENT)
+        .await
+        .unwrap();
+}
+
+#[tokio::test(flavor = "multi_thread")]
+async fn cache() {
+    run_wasi(test_programs_artifacts::CLIENT_CACHE_COMPONENT)
+        .await
+        .unwrap();
+}
+
+/// Serve a body of 1024 bytes, dropping the connection after 512 bytes unless a range is
+/// requested. Only `/etag` responses have a validator.
+fn serve_flaky() -> std::io::Result<std::net::SocketAddr> {
+    use std::io::{BufRead, BufReader, Write};
+
+    let listener = std::net::TcpListener::bind("127.0.0.1:0")?;
+    let addr = listener.local_addr()?;
+    std::thread::spawn(move || {
+        let body = (0..1024).map(|i| (i % 251) as u8).collect::<Vec<_>>();
+        for stream in listener.incoming() {
+            let Ok(mut stream) = stream else { break };
+            let mut reader = BufReader::new(stream.try_clone().unwrap());
+            let mut head = vec![];
+            loop {
+                let mut line = String::new();
+                if reader.read_line(&mut line).unwrap() == 0 || line == "\r\n" {
+                    break;
+                }
+                head.push(line.trim_end().to_ascii_lowercase());
+            }
+            let validator = match head[0].split(' ').nth(1) {
+                Some("/etag") => "ETag: \"v1\"\r\n",
+                _ => "",
+            };
+            let start = head.iter().find_map(|line| {
+                let range = line.strip_prefix("range: bytes=")?;
+                range.trim_end_matches('-').parse::<usize>().ok()
+            });
+            let _ = match start {
+                None => {
+                    let head =
+                        format!("HTTP/1.1 200 OK\r\nContent-Length: 1024\r\n{validator}\r\n");
+                    stream
+                        .write_all(head.as_bytes())
+                        .and_then(|_| stream.write_all(&body[..512]))
+                }
+                Some(start) => {
+                    let head = format!(
+                        "HTTP/1.1 206 Partial Content\r\nContent-Length: {}\r\n\
+                         Content-Range: bytes {start}-1023/1024\r\n{validator}\r\n",
+                        1024 - start
+                    );
+                    stream
+                        .write_all(head.as_bytes())
+                        .and_then(|_| stream.write_all(&body[start..]))
+                }
+            };
+            let _ = stream.shutdown(std::net::Shutdown::Both);
+        }
+    });
+    Ok(addr)
+}
+
+#[tokio::test(flavor = "multi_thread")]
+async fn download_resume() {
+    let addr = serve_flaky().unwrap().to_string();
+    run_wasi_with_env(
+        test_programs_artifacts::CLIENT_DOWNLOAD_RESUME_COMPONENT,
+        &[("SERVER_ADDR", &addr)],
+    )
+    .await
+    .unwrap();
+}
@@ -1,3 +1,4 @@
+mod bench;
 mod client;
 mod server;
 
@@ -42,7 +43,10 @@ impl WasiHttpView for Ctx {
     }
 }
 
-fn new_component(component_filename: &str) -> Result<(Store<Ctx>, Component, Linker<Ctx>)> {
+fn new_component(
+    component_filename: &str,
+    env: &[(&str, &str)],
+) -> Result<(Store<Ctx>, Component, Linker<Ctx>)> {
     let mut config = Config::new();
     config.wasm_backtrace_details(wasmtime::WasmBacktraceDetails::Enable);
     config.wasm_component_model(true);
@@ -55,6 +59,7 @@ fn new_component(component_filename: &str) -> Result<(Store<Ctx>, Component, Lin
         table: ResourceTable::new(),
         wasi: WasiCtxBuilder::new()
             .inherit_stdio()
+            .envs(env)
             .preopened_dir("./tests/all/fixtures", ".", DirPerms::READ, FilePerms::READ)?
             .build(),
         http: WasiHttpCtx::new(),
@@ -72,7 +77,7 @@ pub async fn run_wasi_http(
     component_filename: &str,
     req: hyper::Request<BoxBody<Bytes, hyper::Error>>,
 ) -> Result<Result<hyper::Response<Collected<Bytes>>, ErrorCode>> {
-    let (mut store, component, linker) = new_component(component_filename)?;
+    let (mut store, component, linker) = new_component(component_filename, &[])?;
 
     let proxy = Proxy::instantiate_async(&mut store, &component, &linker).await?;
 
@@ -112,7 +117,11 @@ pub async fn run_wasi_http(
 }
 
 pub async fn run_wasi(component_filename: &str) -> Result<()> {
-    let (mut store, component, linker) = new_component(component_filename)?;
+    run_wasi_with_env(component_filename, &[]).await
+}
+
+pub async fn run_wasi_with_env(component_filename: &str, env: &[(&str, &str)]) -> Result<()> {
+    let (mut store, component, linker) = new_component(component_filename, env)?;
 
     let command = Command::instantiate_async(&mut store, &component, &linker).await?;
     command
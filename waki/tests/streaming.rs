@@ -173,3 +173,19 @@ fn test_empty_form() {
     // Should only contain final boundary
     assert!(output.contains("--FormBoundary"));
 }
+
+#[test]
+fn test_body_reader_buf_read() {
+    use std::io::BufRead;
+
+    let resp = waki::Response::builder()
+        .body("first\nsecond\nthird")
+        .build()
+        .expect("Failed to build response");
+    let lines = resp
+        .into_reader()
+        .lines()
+        .collect::<std::io::Result<Vec<_>>>()
+        .expect("Failed to read lines");
+    assert_eq!(lines, ["first", "second", "third"]);
+}